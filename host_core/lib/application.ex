@@ -74,6 +74,7 @@ defmodule HostCore.Application do
     :ets.new(:vhost_config_table, [:named_table, :set, :public])
     :ets.new(:policy_table, [:named_table, :set, :public])
     :ets.new(:module_cache, [:named_table, :set, :public])
+    :ets.new(:actor_call_metrics, [:named_table, :set, :public])
   end
 
   defp mount_supervisor_tree(config) do
@@ -265,7 +266,14 @@ defmodule HostCore.Application do
   end
 
   defp ensure_booleans(config) do
-    bool_keys = [:config_service_enabled, :ctl_tls, :rpc_tls, :enable_ipv6, :enable_start_from_fs]
+    bool_keys = [
+      :config_service_enabled,
+      :ctl_tls,
+      :rpc_tls,
+      :enable_ipv6,
+      :enable_start_from_fs,
+      :enable_local_actor_calls
+    ]
 
     Enum.reduce(bool_keys, config, fn key, config ->
       old = Map.get(config, key, nil)
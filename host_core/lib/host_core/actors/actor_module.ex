@@ -18,11 +18,16 @@ defmodule HostCore.Actors.ActorModule do
   alias HostCore.Policy.Manager, as: PolicyManager
   alias HostCore.Vhost.VirtualHost
   alias HostCore.WasmCloud.Native
+  alias HostCore.WasmCloud.Runtime.CallContext
 
   require OpenTelemetry.Tracer, as: Tracer
 
   @chunk_threshold 900 * 1024
   @thirty_seconds 30_000
+  @drain_timeout_ms 2_000
+  @health_check_timeout_ms 1_000
+  # holds the call metric totals of every running actor instance, keyed by its pid
+  @call_metrics_table :actor_call_metrics
   @perform_invocation "perform_invocation"
   @rpc_event_prefix "wasmbus.rpcevt"
 
@@ -129,6 +134,30 @@ defmodule HostCore.Actors.ActorModule do
     end
   end
 
+  @doc """
+  Returns the totals of the `t:HostCore.WasmCloud.Runtime.call_metrics/0` of the calls the actor completed,
+  along with the number of `calls`. Metrics an actor never reported (e.g. `fuel_consumed` on a runtime
+  without fuel metering) are left out. The actor keeps its totals in ETS after each call, so reading
+  them never waits on an actor busy handling an invocation
+  """
+  def call_metrics(pid) do
+    case :ets.lookup(@call_metrics_table, pid) do
+      [{^pid, totals}] -> totals
+      [] -> %{}
+    end
+  end
+
+  @doc """
+  Combines two sets of call metric totals. `peak_memory_bytes` is the larger of the two, every other
+  metric adds up
+  """
+  def merge_call_metrics(totals, other) do
+    Map.merge(totals, other, fn
+      :peak_memory_bytes, a, b -> max(a, b)
+      _name, a, b -> a + b
+    end)
+  end
+
   @doc """
   Halts the actor module corresponding to the supplied process ID. This will attempt a graceful termination
   and will try and emit an `actor_stopped` event.
@@ -161,6 +190,9 @@ defmodule HostCore.Actors.ActorModule do
 
     case start_actor(lattice_prefix, host_id, claims, bytes, oci, annotations) do
       {:ok, agent} ->
+        Process.send_after(self(), :do_health, 5_000)
+        :timer.send_interval(@thirty_seconds, self(), :do_health)
+
         {:ok, agent, {:continue, :register_actor}}
 
       {:error, _e} ->
@@ -177,6 +209,26 @@ defmodule HostCore.Actors.ActorModule do
   end
 
   @impl GenServer
+  def handle_info(:do_health, agent) do
+    aref = Agent.get(agent, fn content -> content.actor_reference end)
+
+    # the outcome arrives as an `:actor_health` message. An actor too busy to queue the probe is
+    # checked again next time
+    _ = HostCore.WasmCloud.Runtime.health_check_actor(aref, @health_check_timeout_ms)
+    {:noreply, agent}
+  end
+
+  def handle_info({:actor_health, health}, agent) do
+    previous = Agent.get(agent, fn content -> content.healthy end)
+    Agent.update(agent, fn content -> %State{content | healthy: health.healthy} end)
+
+    agent
+    |> Agent.get(fn content -> content end)
+    |> publish_health_status(health, previous)
+
+    {:noreply, agent}
+  end
+
   def handle_info(_, state) do
     # drain unmatched messages to avoid box overflow
     {:noreply, state}
@@ -199,10 +251,12 @@ defmodule HostCore.Actors.ActorModule do
 
       runtime_pid = Agent.get(agent, fn a -> a.runtime_pid end)
 
-      # TODO: do we need to make a call into the runtime to "un"compile the previous
-      # actor reference's bytes, e.g. `uncache_actor(old_aref)`
-      case HostCore.WasmCloud.Runtime.Server.precompile_actor(runtime_pid, bytes) do
-        {:ok, aref} ->
+      aref = Agent.get(agent, fn content -> content.actor_reference end)
+
+      # the runtime checks the new module is a newer revision of the same actor and swaps it in behind
+      # the existing reference, calls in flight finish on the old module
+      case HostCore.WasmCloud.Runtime.Server.update_actor(runtime_pid, aref, bytes) do
+        :ok ->
           publish_actor_updated(
             config.lattice_prefix,
             config.host_key,
@@ -211,7 +265,7 @@ defmodule HostCore.Actors.ActorModule do
             instance_id
           )
 
-          Agent.update(agent, fn state -> %State{state | actor_reference: aref} end)
+          Agent.update(agent, fn state -> %State{state | claims: claims, ociref: oci} end)
 
           Logger.info("Actor #{claims.public_key} live update complete",
             actor_id: claims.public_key,
@@ -289,6 +343,19 @@ defmodule HostCore.Actors.ActorModule do
       actor_id: public_key
     )
 
+    # no other actor calls this one directly while it drains
+    _ = HostCore.WasmCloud.Runtime.unregister_local_actor(contents.actor_reference)
+
+    case HostCore.WasmCloud.Runtime.drain_actor(contents.actor_reference, @drain_timeout_ms) do
+      :ok ->
+        :ok
+
+      {:error, e} ->
+        Logger.warn("Actor #{public_key} stopped with calls still in flight: #{e}",
+          actor_id: public_key
+        )
+    end
+
     publish_actor_stopped(host_id, lattice_prefix, public_key, instance_id, annotations)
 
     # PRO TIP - if you return :normal here as the stop reason, the GenServer will NOT auto-terminate
@@ -298,6 +365,19 @@ defmodule HostCore.Actors.ActorModule do
     {:stop, :shutdown, :ok, agent}
   end
 
+  # runs when the actor is halted as well as when it crashes in one of its callbacks
+  @impl true
+  def terminate(_reason, agent) do
+    :ets.delete(@call_metrics_table, self())
+
+    # a crashed actor must not stay routable for direct calls from other actors
+    if Process.alive?(agent) do
+      agent
+      |> Agent.get(fn content -> content.actor_reference end)
+      |> HostCore.WasmCloud.Runtime.unregister_local_actor()
+    end
+  end
+
   # Triggered when the actor RPC server receives an inbound message on wasmbus.rpc.{lattice}.{actor}
   @impl true
   def handle_call(
@@ -625,6 +705,10 @@ defmodule HostCore.Actors.ActorModule do
 
     case HostCore.WasmCloud.Runtime.Server.precompile_actor(runtime_pid, bytes) do
       {:ok, aref} ->
+        if VirtualHost.local_actor_calls?(VirtualHost.config(host_id)) do
+          HostCore.WasmCloud.Runtime.register_local_actor(aref)
+        end
+
         iid = UUID.uuid4()
         ClaimsManager.put_claims(host_id, lattice_prefix, claims)
         ActorRpcSupervisor.start_or_reuse_consumer_supervisor(lattice_prefix, claims)
@@ -667,9 +751,18 @@ defmodule HostCore.Actors.ActorModule do
     runtime_pid = Agent.get(agent, fn a -> a.runtime_pid end)
     aref = Agent.get(agent, fn a -> a.actor_reference end)
 
-    call_context =
-      Tracer.current_span_ctx()
-      |> :erlang.term_to_binary()
+    origin = token.invocation["origin"] || %{}
+
+    {:ok, call_context} =
+      HostCore.WasmCloud.Runtime.encode_call_context(%CallContext{
+        trace_context: Map.new(:otel_propagator_text_map.inject([])),
+        caller: %{
+          public_key: origin["public_key"] || "",
+          contract_id: origin["contract_id"] || "",
+          link_name: origin["link_name"] || ""
+        },
+        invocation_id: token.invocation["id"]
+      })
 
     ir =
       case HostCore.WasmCloud.Runtime.Server.invoke_actor(
@@ -677,9 +770,12 @@ defmodule HostCore.Actors.ActorModule do
              aref,
              operation,
              payload,
-             call_context
+             call_context,
+             token.invocation["id"]
            ) do
-        {:ok, msg} ->
+        {:ok, msg, metrics} ->
+          record_call_metrics(metrics)
+
           chunk_inv_response(%{
             msg: msg,
             invocation_id: token.invocation["id"],
@@ -687,6 +783,24 @@ defmodule HostCore.Actors.ActorModule do
             content_length: byte_size(msg)
           })
 
+        {:error, :busy} ->
+          %{
+            msg: <<>>,
+            error: "Actor #{token.invocation["target"]["public_key"]} is busy, try again later",
+            invocation_id: token.invocation["id"],
+            instance_id: token.iid,
+            content_length: 0
+          }
+
+        {:error, :timeout} ->
+          %{
+            msg: <<>>,
+            error: "Actor #{token.invocation["target"]["public_key"]} timed out",
+            invocation_id: token.invocation["id"],
+            instance_id: token.iid,
+            content_length: 0
+          }
+
         {:error, msg} ->
           %{
             msg: <<>>,
@@ -700,6 +814,15 @@ defmodule HostCore.Actors.ActorModule do
     {token, ir}
   end
 
+  defp record_call_metrics(metrics) do
+    reported = for {name, value} <- metrics, value != nil, into: %{}, do: {name, value}
+
+    Tracer.set_attributes(for {name, value} <- reported, do: {"actor_call.#{name}", value})
+
+    totals = merge_call_metrics(call_metrics(self()), Map.put(reported, :calls, 1))
+    :ets.insert(@call_metrics_table, {self(), totals})
+  end
+
   def publish_oci_map(_host_id, _lattice_prefix, "", _pk) do
     # No Op
   end
@@ -802,6 +925,24 @@ defmodule HostCore.Actors.ActorModule do
     |> CloudEvent.publish(prefix)
   end
 
+  defp publish_health_status(state, health, previous_health_check_passed) do
+    evt =
+      cond do
+        health.healthy and not previous_health_check_passed -> "actor_health_check_passed"
+        not health.healthy and previous_health_check_passed -> "actor_health_check_failed"
+        true -> "actor_health_check_status"
+      end
+
+    %{
+      public_key: state.claims.public_key,
+      instance_id: state.instance_id,
+      healthy: health.healthy,
+      message: health.message
+    }
+    |> CloudEvent.new(evt, state.host_id)
+    |> CloudEvent.publish(state.lattice_prefix)
+  end
+
   def publish_actor_stopped(host_id, lattice_prefix, actor_pk, instance_id, annotations) do
     %{
       public_key: actor_pk,
@@ -445,6 +445,20 @@ defmodule HostCore.Actors.ActorSupervisor do
     Enum.reduce(actors_on_host, %{}, fn {pk, _pid}, acc -> Map.update(acc, pk, 1, &(&1 + 1)) end)
   end
 
+  @doc """
+  Maps the public key of each actor on the host to the totals of the call metrics of all of its
+  instances, see `HostCore.Actors.ActorModule.call_metrics/1`
+  """
+  @spec all_actor_metrics_for_hb(host_id :: String.t()) :: %{String.t() => map()}
+  def all_actor_metrics_for_hb(host_id) do
+    for {pk, pids} <- all_actors(host_id), into: %{} do
+      {pk,
+       pids
+       |> Enum.map(&ActorModule.call_metrics/1)
+       |> Enum.reduce(%{}, &ActorModule.merge_call_metrics/2)}
+    end
+  end
+
   @doc """
   Produces a list of tuples containing the pid of the child actor, its public key, and its
   OCI reference.
@@ -66,8 +66,26 @@ defmodule HostCore.Vhost.ConfigPlan do
           {:structured_log_level, "WASMCLOUD_STRUCTURED_LOG_LEVEL",
            required: false, map: &string_to_loglevel/1},
           {:enable_ipv6, "WASMCLOUD_ENABLE_IPV6", required: false, map: &string_to_bool/1},
+          {:enable_local_actor_calls, "WASMCLOUD_ENABLE_LOCAL_ACTOR_CALLS",
+           required: false, map: &string_to_bool/1},
           {:enable_start_from_fs, "WASMCLOUD_ALLOW_FILE_LOAD",
            required: false, map: &string_to_bool/1},
+          {:module_cache_dir, "WASMCLOUD_MODULE_CACHE_DIR", required: false},
+          {:engine_opt_level, "WASMCLOUD_ENGINE_OPT_LEVEL", required: false},
+          {:engine_parallel_compilation, "WASMCLOUD_ENGINE_PARALLEL_COMPILATION",
+           required: false, map: &string_to_bool/1},
+          {:engine_pooling_allocator, "WASMCLOUD_ENGINE_POOLING_ALLOCATOR",
+           required: false, map: &string_to_bool/1},
+          {:engine_max_wasm_stack, "WASMCLOUD_ENGINE_MAX_WASM_STACK",
+           required: false, map: &String.to_integer/1},
+          {:engine_wasm_proposals, "WASMCLOUD_ENGINE_WASM_PROPOSALS",
+           required: false, map: &String.split(&1, ",")},
+          {:engine_guest_profiling, "WASMCLOUD_ENGINE_GUEST_PROFILING",
+           required: false, map: &string_to_bool/1},
+          {:engine_interruptible_calls, "WASMCLOUD_ENGINE_INTERRUPTIBLE_CALLS",
+           required: false, map: &string_to_bool/1},
+          {:engine_fuel_metering, "WASMCLOUD_ENGINE_FUEL_METERING",
+           required: false, map: &string_to_bool/1},
           {:policy_topic, "WASMCLOUD_POLICY_TOPIC", required: false},
           {:policy_changes_topic, "WASMCLOUD_POLICY_CHANGES_TOPIC", required: false},
           {:policy_timeout_ms, "WASMCLOUD_POLICY_TIMEOUT",
@@ -111,7 +129,17 @@ defmodule HostCore.Vhost.ConfigPlan do
       # DEPRECATED
       {:structured_log_level, "structured_log_level", required: false, default: nil},
       {:enable_ipv6, "enable_ipv6", required: false, default: false},
+      {:enable_local_actor_calls, "enable_local_actor_calls", required: false, default: false},
       {:enable_start_from_fs, "enable_start_from_fs", required: false, default: false},
+      {:module_cache_dir, "module_cache_dir", required: false, default: nil},
+      {:engine_opt_level, "engine_opt_level", required: false, default: nil},
+      {:engine_parallel_compilation, "engine_parallel_compilation", required: false, default: nil},
+      {:engine_pooling_allocator, "engine_pooling_allocator", required: false, default: nil},
+      {:engine_max_wasm_stack, "engine_max_wasm_stack", required: false, default: nil},
+      {:engine_wasm_proposals, "engine_wasm_proposals", required: false, default: nil},
+      {:engine_guest_profiling, "engine_guest_profiling", required: false, default: nil},
+      {:engine_interruptible_calls, "engine_interruptible_calls", required: false, default: nil},
+      {:engine_fuel_metering, "engine_fuel_metering", required: false, default: nil},
       {:policy_topic, "policy_topic", required: false},
       {:policy_changes_topic, "policy_changes_topic", required: false},
       {:policy_timeout_ms, "policy_timeout_ms", required: false, default: 1_000}
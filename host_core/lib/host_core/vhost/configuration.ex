@@ -30,7 +30,17 @@ defmodule HostCore.Vhost.Configuration do
           rpc_tls: boolean(),
           config_service_enabled: boolean(),
           enable_ipv6: boolean(),
+          enable_local_actor_calls: boolean(),
           enable_start_from_fs: boolean(),
+          module_cache_dir: String.t() | nil,
+          engine_opt_level: String.t() | atom() | nil,
+          engine_parallel_compilation: boolean() | nil,
+          engine_pooling_allocator: boolean() | nil,
+          engine_max_wasm_stack: pos_integer() | nil,
+          engine_wasm_proposals: [String.t() | atom()] | nil,
+          engine_guest_profiling: boolean() | nil,
+          engine_interruptible_calls: boolean() | nil,
+          engine_fuel_metering: boolean() | nil,
           cluster_issuers: [String.t()],
           log_level: atom(),
           prov_rpc_tls: boolean(),
@@ -73,7 +83,17 @@ defmodule HostCore.Vhost.Configuration do
     :rpc_tls,
     :config_service_enabled,
     :enable_ipv6,
+    :enable_local_actor_calls,
     :enable_start_from_fs,
+    :module_cache_dir,
+    :engine_opt_level,
+    :engine_parallel_compilation,
+    :engine_pooling_allocator,
+    :engine_max_wasm_stack,
+    :engine_wasm_proposals,
+    :engine_guest_profiling,
+    :engine_interruptible_calls,
+    :engine_fuel_metering,
     :cluster_issuers,
     :log_level,
     :prov_rpc_tls,
@@ -47,6 +47,10 @@ defmodule HostCore.Vhost.Heartbeats do
       config.host_key
       |> ActorSupervisor.all_actors_for_hb()
 
+    actor_metrics =
+      config.host_key
+      |> ActorSupervisor.all_actor_metrics_for_hb()
+
     providers =
       config.host_key
       |> ProviderSupervisor.all_providers_for_hb()
@@ -64,6 +68,7 @@ defmodule HostCore.Vhost.Heartbeats do
     CloudEvent.new(
       %{
         actors: actors,
+        actor_metrics: actor_metrics,
         providers: providers,
         labels: state.labels,
         friendly_name: state.friendly_name,
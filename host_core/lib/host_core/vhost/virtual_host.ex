@@ -23,6 +23,20 @@ defmodule HostCore.Vhost.VirtualHost do
   alias HostCore.WasmCloud.Native
   alias Timex.Format.Duration.Formatters.Humanized
 
+  # the engine settings that are read from configuration as strings, see
+  # `HostCore.WasmCloud.Runtime.Config`
+  @opt_levels %{"none" => :none, "speed" => :speed, "speed_and_size" => :speed_and_size}
+  @wasm_proposals %{
+    "simd" => :simd,
+    "relaxed_simd" => :relaxed_simd,
+    "bulk_memory" => :bulk_memory,
+    "reference_types" => :reference_types,
+    "multi_value" => :multi_value,
+    "threads" => :threads,
+    "multi_memory" => :multi_memory,
+    "memory64" => :memory64
+  }
+
   defmodule State do
     @moduledoc """
     The state of the virtual host is a record containing the following fields:
@@ -59,7 +73,19 @@ defmodule HostCore.Vhost.VirtualHost do
   @spec init(Configuration.t()) ::
           {:ok, HostCore.Vhost.VirtualHost.State.t(),
            {:continue, :load_supp_config | :publish_started}}
+          | {:stop, {:invalid_config, binary()}}
   def init(config) do
+    case engine_settings(config) do
+      {:ok, engine_settings} ->
+        start_host(config, engine_settings)
+
+      {:error, e} ->
+        Logger.error("Virtual Host #{config.host_key} failed to start: #{e}")
+        {:stop, {:invalid_config, e}}
+    end
+  end
+
+  defp start_host(config, engine_settings) do
     Process.flag(:trap_exit, true)
 
     case LatticeRoot.start_lattice(config) do
@@ -110,11 +136,23 @@ defmodule HostCore.Vhost.VirtualHost do
       ]
     })
 
+    local_actor_calls = local_actor_calls?(config)
+
+    if config.enable_local_actor_calls == true && !local_actor_calls do
+      Logger.warn("Local actor calls disabled because a policy topic is configured")
+    end
+
     # TODO - gracefully bail if the runtime couldn't be started
     {:ok, runtime} =
-      HostCore.WasmCloud.Runtime.Server.start_link(%HostCore.WasmCloud.Runtime.Config{
-        host_id: config.host_key
-      })
+      %HostCore.WasmCloud.Runtime.Config{
+        host_id: config.host_key,
+        local_actor_calls: local_actor_calls,
+        cluster_seed: config.cluster_seed,
+        cluster_issuers: config.cluster_issuers,
+        module_cache_dir: config.module_cache_dir
+      }
+      |> struct(engine_settings)
+      |> HostCore.WasmCloud.Runtime.Server.start_link()
 
     Logger.info(
       "Started wasmCloud internal wasm runtime v#{HostCore.WasmCloud.Runtime.Server.version(runtime)}"
@@ -207,6 +245,15 @@ defmodule HostCore.Vhost.VirtualHost do
     GenServer.call(pid, :get_runtime)
   end
 
+  @doc """
+  Returns whether actors on a host with the given config call each other directly inside its runtime.
+  Direct calls between local actors bypass the policy service, so they are only allowed without one
+  """
+  def local_actor_calls?(config) do
+    config.enable_local_actor_calls == true &&
+      HostCore.Policy.Manager.policy_topic(config) == :policy_eval_disabled
+  end
+
   # Obtains -registry- credentials
   def get_creds(host_id, type, ref) do
     case lookup(host_id) do
@@ -436,6 +483,7 @@ defmodule HostCore.Vhost.VirtualHost do
     Logger.debug("Host stop requested through process termination: #{inspect(reason)}")
 
     do_purge(state)
+    _ = HostCore.WasmCloud.Runtime.Server.drain(state.wasm_runtime, 1_000)
     publish_host_stopped(state)
     :timer.sleep(300)
   end
@@ -505,6 +553,52 @@ defmodule HostCore.Vhost.VirtualHost do
     end
   end
 
+  # engine settings the host wasn't configured with keep the runtime's defaults. Values read back from
+  # the JSON config file are strings rather than atoms
+  defp engine_settings(config) do
+    with {:ok, opt_level} <-
+           engine_option(:engine_opt_level, config.engine_opt_level, @opt_levels),
+         {:ok, wasm_proposals} <- engine_wasm_proposals(config.engine_wasm_proposals) do
+      {:ok,
+       [
+         opt_level: opt_level,
+         parallel_compilation: config.engine_parallel_compilation,
+         pooling_allocator: config.engine_pooling_allocator,
+         max_wasm_stack: config.engine_max_wasm_stack,
+         wasm_proposals: wasm_proposals,
+         guest_profiling: config.engine_guest_profiling,
+         interruptible_calls: config.engine_interruptible_calls,
+         fuel_metering: config.engine_fuel_metering
+       ]
+       |> Enum.reject(fn {_key, value} -> is_nil(value) end)}
+    end
+  end
+
+  defp engine_wasm_proposals(nil), do: {:ok, nil}
+
+  defp engine_wasm_proposals(proposals) do
+    Enum.reduce_while(proposals, {:ok, []}, fn proposal, {:ok, acc} ->
+      case engine_option(:engine_wasm_proposals, proposal, @wasm_proposals) do
+        {:ok, proposal} -> {:cont, {:ok, acc ++ [proposal]}}
+        {:error, _e} = error -> {:halt, error}
+      end
+    end)
+  end
+
+  defp engine_option(_option, nil, _known), do: {:ok, nil}
+
+  defp engine_option(option, value, known) do
+    case Map.fetch(known, value |> to_string() |> String.trim() |> String.downcase()) do
+      {:ok, atom} ->
+        {:ok, atom}
+
+      :error ->
+        {:error,
+         "invalid #{option} #{inspect(value)}, expected one of " <>
+           (known |> Map.keys() |> Enum.sort() |> Enum.join(", "))}
+    end
+  end
+
   defp get_env_host_labels do
     keys =
       System.get_env() |> Map.keys() |> Enum.filter(fn k -> String.starts_with?(k, "HOST_") end)
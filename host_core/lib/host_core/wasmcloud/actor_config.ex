@@ -0,0 +1,25 @@
+defmodule HostCore.WasmCloud.Runtime.ActorConfig do
+  @moduledoc ~S"""
+  Limits and environment applied to an actor when it is started on a `WasmCloud.Runtime`.
+
+  ## Options
+    * `:max_concurrency` - how many calls the actor may serve at once, `nil` for no limit
+    * `:max_queued` - how many calls may wait for the actor once it is serving `:max_concurrency` calls.
+      Past that, calls are rejected with `{:error, :busy}`. `nil` lets any number of calls wait
+    * `:wasi` - the actor's WASI environment, see `HostCore.WasmCloud.Runtime.WasiConfig`. When `nil` the
+      actor gets an empty environment and its stdout and stderr are discarded
+
+  ## Example
+      iex> _config = %HostCore.WasmCloud.Runtime.ActorConfig{max_concurrency: 4, max_queued: 100}
+  """
+
+  defstruct max_concurrency: nil,
+            max_queued: nil,
+            wasi: nil
+
+  @type t :: %__MODULE__{
+          max_concurrency: pos_integer() | nil,
+          max_queued: non_neg_integer() | nil,
+          wasi: HostCore.WasmCloud.Runtime.WasiConfig.t() | nil
+        }
+end
@@ -0,0 +1,35 @@
+defmodule HostCore.WasmCloud.Runtime.CallContext do
+  @moduledoc ~S"""
+  What the host knows about an actor call, passed to `HostCore.WasmCloud.Runtime.call_actor/6` in its
+  encoded form (see `HostCore.WasmCloud.Runtime.encode_call_context/1`). The actor hands it back with each
+  of its host calls, where it reaches the runtime server decoded. Contexts are versioned, anything that
+  isn't an encoded context is passed through to the actor untouched but reaches host calls as `nil`.
+
+  ## Fields
+    * `:trace_context` - the propagated trace context, as produced by `:otel_propagator_text_map.inject/1`
+    * `:deadline_ms` - Unix time in milliseconds after which the caller no longer waits for the result
+    * `:caller` - the actor or provider making the call. Calls delivered directly between actors on the
+      runtime name the calling actor here
+    * `:invocation_id` - the ID of the invocation being handled
+
+  ## Example
+      iex> _context = %HostCore.WasmCloud.Runtime.CallContext{
+      ...>   trace_context: Map.new(:otel_propagator_text_map.inject([])),
+      ...>   invocation_id: UUID.uuid4()
+      ...> }
+  """
+
+  defstruct trace_context: %{},
+            deadline_ms: nil,
+            caller: nil,
+            invocation_id: nil
+
+  @type caller :: %{public_key: binary(), contract_id: binary(), link_name: binary()}
+
+  @type t :: %__MODULE__{
+          trace_context: %{binary() => binary()},
+          deadline_ms: non_neg_integer() | nil,
+          caller: caller() | nil,
+          invocation_id: binary() | nil
+        }
+end
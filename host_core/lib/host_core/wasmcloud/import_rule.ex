@@ -0,0 +1,25 @@
+defmodule HostCore.WasmCloud.Runtime.ImportRule do
+  @moduledoc ~S"""
+  Matches the imports of actor modules for a `WasmCloud.Runtime`'s import policy, see
+  `HostCore.WasmCloud.Runtime.Config`.
+
+  Both fields may use `*` as a wildcard. The `:namespace` of a core module import is its module name and
+  its `:function` the imported name. A component import is matched on its name alone by rules without a
+  `:function`. A rule without a `:function` matches every import from the namespace
+
+  ## Example
+      iex> _deny_sockets = %HostCore.WasmCloud.Runtime.ImportRule{
+      ...>   namespace: "wasi_snapshot_preview1",
+      ...>   function: "sock_*"
+      ...> }
+  """
+
+  @enforce_keys [:namespace]
+  defstruct namespace: nil,
+            function: nil
+
+  @type t :: %__MODULE__{
+          namespace: binary(),
+          function: binary() | nil
+        }
+end
@@ -5,6 +5,9 @@ defmodule HostCore.WasmCloud.Native do
   alias HostCore.WasmCloud.Native
 
   def extract_claims(_bytes), do: error()
+  def inspect_module(_bytes), do: error()
+  def encode_call_context(_context), do: error()
+  def decode_call_context(_bytes), do: error()
   def generate_key(_keytype), do: error()
 
   def pk_from_seed(_seed), do: error()
@@ -36,9 +39,29 @@ defmodule HostCore.WasmCloud.Native do
 
   # Wasm Runtime
   def runtime_new(_config), do: error()
-  def start_actor(_runtime_resource, _bytes), do: error()
+  def start_actor(_runtime_resource, _bytes, _actor_config), do: error()
+  def update_actor(_runtime_resource, _actor_resource, _bytes), do: error()
   def version(_runtime_resource), do: error()
-  def call_actor(_actor_resource, _operation, _payload, _call_context, _from), do: error()
+  def engine_config(_runtime_resource), do: error()
+  def actor_log_stats(_runtime_resource), do: error()
+  def runtime_stats(_runtime_resource), do: error()
+  def module_cache_stats(_runtime_resource), do: error()
+  def purge_module_cache(_runtime_resource), do: error()
+  def reseed_numbergen(_runtime_resource, _seed), do: error()
+  def call_actor(_actor_resource, _operation, _payload, _call_context, _invocation_id, _from),
+    do: error()
+
+  def cancel_actor_call(_call_resource), do: error()
+  def register_local_actor(_actor_resource), do: error()
+  def unregister_local_actor(_actor_resource), do: error()
+  def drain_actor(_actor_resource, _timeout_ms), do: error()
+  def health_check_actor(_actor_resource, _timeout_ms), do: error()
+  def start_recording(_actor_resource, _path), do: error()
+  def stop_recording(_actor_resource), do: error()
+  def replay_recording(_bytes, _path), do: error()
+  def start_profiling(_actor_resource, _path, _calls), do: error()
+  def stop_profiling(_actor_resource), do: error()
+  def drain_runtime(_runtime_resource, _timeout_ms), do: error()
   def instance_receive_callback_result(_callback_token, _success, _result), do: error()
 
   # When the NIF is loaded, it will override functions in this module.
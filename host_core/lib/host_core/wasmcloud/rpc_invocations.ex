@@ -205,18 +205,21 @@ defmodule HostCore.WasmCloud.RpcInvocations do
     res
   end
 
-  defp publish_invocation_result(
-         actor,
-         namespace,
-         binding,
-         operation,
-         payload_bytes,
-         target_type,
-         target_key,
-         res,
-         prefix,
-         host_id
-       ) do
+  @doc """
+  Publishes the `invocation_succeeded` or `invocation_failed` event for an invocation made by an actor
+  """
+  def publish_invocation_result(
+        actor,
+        namespace,
+        binding,
+        operation,
+        payload_bytes,
+        target_type,
+        target_key,
+        res,
+        prefix,
+        host_id
+      ) do
     evt_type =
       case res do
         {:ok, _} ->
@@ -9,6 +9,24 @@ defmodule HostCore.WasmCloud.Runtime do
           reference: reference()
         }
 
+  @typedoc """
+  What a completed actor call cost. `guest_cpu_time_us` is the CPU time spent running the call, waiting on
+  host calls answered by the host costs none. `fuel_consumed` is `nil` unless the runtime was started with
+  `fuel_metering: true`. `peak_memory_bytes` is the largest size the actor's memory reached during the
+  call, counting the size it was instantiated with
+  """
+  @type call_metrics :: %{
+          wall_time_us: non_neg_integer(),
+          guest_cpu_time_us: non_neg_integer(),
+          fuel_consumed: non_neg_integer() | nil,
+          peak_memory_bytes: non_neg_integer(),
+          host_calls: non_neg_integer(),
+          bytes_in: non_neg_integer(),
+          bytes_out: non_neg_integer(),
+          host_call_bytes_out: non_neg_integer(),
+          host_call_bytes_in: non_neg_integer()
+        }
+
   defstruct resource: nil,
             # The actual NIF store resource.
             # Normally the compiler will happily do stuff like inlining the
@@ -56,15 +74,93 @@ defmodule HostCore.WasmCloud.Runtime do
   @doc """
   Has the underlying Rust SDK precompile an actor. Note that this is called "start" to be consistent with
   the terminology throughout the rest of the host, but the wasmCloud SDK is responsible for determining what
-  this means. As such, this will precompile a modul/component and return a reference to it.
+  this means. As such, this will precompile a modul/component and return a reference to it. The actor's
+  concurrency limits are taken from `actor_config`
   """
-  def start_actor(%__MODULE__{resource: rtresource}, bytes) do
-    case HostCore.WasmCloud.Native.start_actor(rtresource, bytes) do
+  def start_actor(
+        %__MODULE__{resource: rtresource},
+        bytes,
+        %HostCore.WasmCloud.Runtime.ActorConfig{} = actor_config
+      ) do
+    case HostCore.WasmCloud.Native.start_actor(rtresource, bytes, actor_config) do
       {:error, err} -> {:error, err}
       resource -> {:ok, ActorReference.__wrap_resource__(resource)}
     end
   end
 
+  @doc """
+  Live updates a running actor, replacing the module behind `actor_reference` with `bytes`. The new module
+  must be signed by the same issuer for the same actor, have a higher revision, use the same ABI and keep
+  the call alias, otherwise the actor is left as it was and an error explains why. Calls already in
+  flight finish on the previous module, the reference itself stays valid
+  """
+  @spec update_actor(__MODULE__.t(), ActorReference.t(), binary()) :: :ok | {:error, binary()}
+  def update_actor(
+        %__MODULE__{resource: rtresource},
+        %ActorReference{resource: actor_resource},
+        bytes
+      ) do
+    HostCore.WasmCloud.Native.update_actor(rtresource, actor_resource, bytes)
+  end
+
+  @doc """
+  Reports what can be learned about a module or component without compiling it: its `sha256`, the `abi`
+  the host would call it through (`:wasmbus`, `:component` or `:unknown` for core modules the runtime
+  can't call), its `imports` and `exports`, the `memories` of its core modules, its custom sections and
+  the contents of its `producers` section
+  """
+  @spec inspect_module(binary()) ::
+          {:ok,
+           %{
+             sha256: binary(),
+             size_bytes: non_neg_integer(),
+             abi: :wasmbus | :component | :unknown,
+             imports: [%{module: binary(), name: binary(), kind: binary()}],
+             exports: [%{name: binary(), kind: binary()}],
+             memories: [
+               %{
+                 initial_pages: non_neg_integer(),
+                 maximum_pages: non_neg_integer() | nil,
+                 memory64: boolean(),
+                 shared: boolean(),
+                 imported: boolean()
+               }
+             ],
+             custom_sections: [%{name: binary(), size_bytes: non_neg_integer()}],
+             producers: %{binary() => [binary()]}
+           }}
+          | {:error, binary()}
+  def inspect_module(bytes) do
+    case HostCore.WasmCloud.Native.inspect_module(bytes) do
+      {:error, err} -> {:error, err}
+      info -> {:ok, info}
+    end
+  end
+
+  @doc """
+  Encodes a `HostCore.WasmCloud.Runtime.CallContext` to be passed to `call_actor/6`
+  """
+  @spec encode_call_context(HostCore.WasmCloud.Runtime.CallContext.t()) ::
+          {:ok, binary()} | {:error, binary()}
+  def encode_call_context(%HostCore.WasmCloud.Runtime.CallContext{} = context) do
+    case HostCore.WasmCloud.Native.encode_call_context(context) do
+      {:error, err} -> {:error, err}
+      bytes -> {:ok, bytes}
+    end
+  end
+
+  @doc """
+  Decodes a call context produced by `encode_call_context/1`, failing for anything else
+  """
+  @spec decode_call_context(binary()) ::
+          {:ok, HostCore.WasmCloud.Runtime.CallContext.t()} | {:error, binary()}
+  def decode_call_context(bytes) do
+    case HostCore.WasmCloud.Native.decode_call_context(bytes) do
+      {:error, err} -> {:error, err}
+      context -> {:ok, context}
+    end
+  end
+
   def version(%__MODULE__{resource: resource}) do
     case HostCore.WasmCloud.Native.version(resource) do
       {:error, _err} -> "??"
@@ -72,21 +168,299 @@ defmodule HostCore.WasmCloud.Runtime do
     end
   end
 
+  @doc """
+  Returns the configuration the runtime's engine is running with, with the defaults of any settings left
+  unset in its `HostCore.WasmCloud.Runtime.Config` filled in. `module_cache_dir` is `nil` when the runtime
+  has no module cache, including when the configured directory couldn't be used
+  """
+  @spec engine_config(__MODULE__.t()) :: %{
+          opt_level: HostCore.WasmCloud.Runtime.Config.opt_level(),
+          parallel_compilation: boolean(),
+          pooling_allocator: boolean(),
+          max_wasm_stack: pos_integer(),
+          wasm_proposals: [HostCore.WasmCloud.Runtime.Config.wasm_proposal()],
+          guest_profiling: boolean(),
+          interruptible_calls: boolean(),
+          fuel_metering: boolean(),
+          module_cache_dir: binary() | nil
+        }
+  def engine_config(%__MODULE__{resource: resource}) do
+    HostCore.WasmCloud.Native.engine_config(resource)
+  end
+
+  @doc """
+  Returns counters for the runtime's actor log queue: lines `delivered` to the host, lines `dropped`
+  and the number of times an actor was `backpressured` waiting for room in the queue
+  """
+  @spec actor_log_stats(__MODULE__.t()) :: %{
+          delivered: non_neg_integer(),
+          dropped: non_neg_integer(),
+          backpressured: non_neg_integer()
+        }
+  def actor_log_stats(%__MODULE__{resource: resource}) do
+    HostCore.WasmCloud.Native.actor_log_stats(resource)
+  end
+
+  @doc """
+  Returns how loaded the runtime is: the actor instances started on it (`actors`, with instance,
+  in-flight and queued call counts keyed by public key in `per_actor`), calls `in_flight`, calls
+  `queued` for one of their actor's concurrency slots, host calls waiting on the host
+  (`pending_callbacks`), the number of native `workers` and an `estimated_utilisation` of them by this
+  runtime, along with the cumulative number of `calls`, `call_errors` (errors returned by actors
+  included) and `calls_cancelled`.
+
+  `estimated_utilisation` is a rough upper bound rather than a measurement: it counts every call in
+  flight that isn't waiting on the host as occupying a worker, and doesn't see other runtimes sharing
+  the workers
+  """
+  @spec stats(__MODULE__.t()) :: %{
+          actors: non_neg_integer(),
+          per_actor: %{
+            binary() => %{
+              instances: non_neg_integer(),
+              in_flight: non_neg_integer(),
+              queued: non_neg_integer()
+            }
+          },
+          in_flight: non_neg_integer(),
+          queued: non_neg_integer(),
+          pending_callbacks: non_neg_integer(),
+          workers: pos_integer(),
+          estimated_utilisation: float(),
+          calls: non_neg_integer(),
+          call_errors: non_neg_integer(),
+          calls_cancelled: non_neg_integer()
+        }
+  def stats(%__MODULE__{resource: resource}) do
+    HostCore.WasmCloud.Native.runtime_stats(resource)
+  end
+
+  @doc """
+  Describes the runtime's compiled-module cache: its `directory`, the `modules` started with it along
+  with the runtime versions and engine configurations each was compiled for (`builds`) and whether one
+  of them is this runtime's (`compatible`), the size on disk of the compiled artifacts and the cache
+  `hits` and `misses` since the runtime was created. An engine configuration is identified by a
+  fingerprint of the settings that change compiled code: the optimization level, the enabled proposals,
+  and whether calls are interruptible, profiled or metered. Returns `nil` if the runtime has no module
+  cache
+  """
+  @spec module_cache_stats(__MODULE__.t()) ::
+          %{
+            directory: binary(),
+            modules: [
+              %{
+                sha256: binary(),
+                public_key: binary(),
+                size_bytes: non_neg_integer(),
+                builds: [%{runtime_version: binary(), engine_fingerprint: binary()}],
+                last_used: non_neg_integer(),
+                compatible: boolean()
+              }
+            ],
+            artifact_bytes: non_neg_integer(),
+            hits: non_neg_integer(),
+            misses: non_neg_integer()
+          }
+          | nil
+  def module_cache_stats(%__MODULE__{resource: resource}) do
+    HostCore.WasmCloud.Native.module_cache_stats(resource)
+  end
+
+  @doc """
+  Empties the runtime's compiled-module cache, returning how many modules were in it. Running actors are
+  unaffected
+  """
+  @spec purge_module_cache(__MODULE__.t()) :: {:ok, non_neg_integer()} | {:error, binary()}
+  def purge_module_cache(%__MODULE__{resource: resource}) do
+    case HostCore.WasmCloud.Native.purge_module_cache(resource) do
+      {:error, err} -> {:error, err}
+      purged -> {:ok, purged}
+    end
+  end
+
+  @doc """
+  Replaces the seed of the builtin numbergen capability, restarting every actor's sequence. Passing `nil`
+  goes back to real randomness
+  """
+  @spec reseed_numbergen(__MODULE__.t(), non_neg_integer() | nil) :: :ok
+  def reseed_numbergen(%__MODULE__{resource: resource}, seed) do
+    HostCore.WasmCloud.Native.reseed_numbergen(resource, seed)
+  end
+
+  @doc """
+  Asks the NIF to invoke the given operation on an actor. The result is delivered asynchronously to the
+  runtime server, which replies to `from` with the call's `t:call_metrics/0`. The returned call
+  reference can be handed to `cancel_actor_call/1` to abandon the invocation before it completes
+  """
   @spec call_actor(
           HostCore.WasmCloud.Runtime.ActorReference.t(),
           binary(),
           binary(),
           binary(),
+          binary(),
           GenServer.from()
-        ) :: :ok
+        ) :: {:ok, reference()} | {:error, binary()} | {:error, :busy}
   def call_actor(
         %HostCore.WasmCloud.Runtime.ActorReference{resource: actor_resource},
         operation,
         payload,
         call_context,
+        invocation_id,
         from
       ) do
-    HostCore.WasmCloud.Native.call_actor(actor_resource, operation, payload, call_context, from)
+    HostCore.WasmCloud.Native.call_actor(
+      actor_resource,
+      operation,
+      payload,
+      call_context,
+      invocation_id,
+      from
+    )
+  end
+
+  @doc """
+  Cancels an in-flight actor call. If the call had not yet completed, its original caller receives
+  `{:error, :cancelled}` and `:ok` is returned, otherwise this returns `:already_completed`. An actor
+  waiting on the host is stopped right away, whereas one running its own code is only interrupted if the
+  runtime was started with `interruptible_calls: true` (see `HostCore.WasmCloud.Runtime.Config`). Without
+  it, the actor keeps running until it next calls the host
+  """
+  @spec cancel_actor_call(reference()) :: :ok | :already_completed
+  def cancel_actor_call(call_ref) do
+    HostCore.WasmCloud.Native.cancel_actor_call(call_ref)
+  end
+
+  @doc """
+  Registers a running actor with its runtime so that, when the runtime was configured with
+  `local_actor_calls`, other actors on it call this one directly rather than over the lattice
+  """
+  @spec register_local_actor(HostCore.WasmCloud.Runtime.ActorReference.t()) :: :ok
+  def register_local_actor(%HostCore.WasmCloud.Runtime.ActorReference{resource: actor_resource}) do
+    HostCore.WasmCloud.Native.register_local_actor(actor_resource)
+  end
+
+  @doc """
+  Stops routing direct local calls to an actor. Draining an actor also unregisters it. Returns whether
+  the actor had been registered
+  """
+  @spec unregister_local_actor(HostCore.WasmCloud.Runtime.ActorReference.t()) :: boolean()
+  def unregister_local_actor(%HostCore.WasmCloud.Runtime.ActorReference{resource: actor_resource}) do
+    HostCore.WasmCloud.Native.unregister_local_actor(actor_resource)
+  end
+
+  @doc """
+  Stops the actor from accepting new calls and waits up to `timeout_ms` for the calls already in flight
+  to finish. Returns an error if calls were still running when the deadline passed
+  """
+  @spec drain_actor(HostCore.WasmCloud.Runtime.ActorReference.t(), non_neg_integer()) ::
+          :ok | {:error, binary()}
+  def drain_actor(%HostCore.WasmCloud.Runtime.ActorReference{resource: actor_resource}, timeout_ms) do
+    HostCore.WasmCloud.Native.drain_actor(actor_resource, timeout_ms)
+  end
+
+  @doc """
+  Asks the actor for its health through the standard health operation, allowing it `timeout_ms` to
+  answer once it holds one of the actor's concurrency slots. The outcome is sent to the calling process
+  as `{:actor_health, %{healthy: boolean(), message: binary() | nil}}`. An actor that fails the call,
+  doesn't answer in time or is draining is reported as unhealthy, with the reason as its message.
+  Returns `{:error, :busy}` without probing when the actor's call queue is full. Any host calls made by
+  the actor go through the runtime server, so this must not be called from within it
+  """
+  @spec health_check_actor(HostCore.WasmCloud.Runtime.ActorReference.t(), non_neg_integer()) ::
+          :ok | {:error, :busy}
+  def health_check_actor(
+        %HostCore.WasmCloud.Runtime.ActorReference{resource: actor_resource},
+        timeout_ms
+      ) do
+    HostCore.WasmCloud.Native.health_check_actor(actor_resource, timeout_ms)
+  end
+
+  @doc """
+  Starts recording every call made on the actor to the file at `path`, replacing it if it exists. Each
+  call is recorded with its payload and call context, the result of every host call the actor made
+  (builtins included) and the actor's response, so that it can be replayed with `replay_recording/2`
+  """
+  @spec start_recording(HostCore.WasmCloud.Runtime.ActorReference.t(), Path.t()) ::
+          :ok | {:error, binary()}
+  def start_recording(
+        %HostCore.WasmCloud.Runtime.ActorReference{resource: actor_resource},
+        path
+      ) do
+    HostCore.WasmCloud.Native.start_recording(actor_resource, path)
+  end
+
+  @doc """
+  Stops recording the actor's calls, returning how many were recorded
+  """
+  @spec stop_recording(HostCore.WasmCloud.Runtime.ActorReference.t()) ::
+          {:ok, non_neg_integer()} | {:error, binary()}
+  def stop_recording(%HostCore.WasmCloud.Runtime.ActorReference{resource: actor_resource}) do
+    case HostCore.WasmCloud.Native.stop_recording(actor_resource) do
+      {:error, err} -> {:error, err}
+      calls -> {:ok, calls}
+    end
+  end
+
+  @doc """
+  Re-runs the calls of a recording on `bytes`, the recorded module or a new build of it, in a runtime of
+  its own. Host calls are answered from the recording, so neither the host nor any provider is involved.
+  The report lists every point at which the actor didn't repeat what was recorded, an empty list of
+  `divergences` means it behaved exactly the same
+  """
+  @spec replay_recording(binary(), Path.t()) ::
+          {:ok,
+           %{
+             actor: binary(),
+             calls: non_neg_integer(),
+             host_calls: non_neg_integer(),
+             module_changed: boolean(),
+             divergences: [binary()]
+           }}
+          | {:error, binary()}
+  def replay_recording(bytes, path) do
+    case HostCore.WasmCloud.Native.replay_recording(bytes, path) do
+      {:error, err} -> {:error, err}
+      report -> {:ok, report}
+    end
+  end
+
+  @doc """
+  Starts sampling the guest stack of the next `calls` calls made on the actor, about once a millisecond.
+  The actor's runtime must have been started with `guest_profiling: true`. The profile is written to
+  `path` by `stop_profiling/1`, in the Gecko format that can be opened in the Firefox profiler
+  """
+  @spec start_profiling(HostCore.WasmCloud.Runtime.ActorReference.t(), Path.t(), pos_integer()) ::
+          :ok | {:error, binary()}
+  def start_profiling(
+        %HostCore.WasmCloud.Runtime.ActorReference{resource: actor_resource},
+        path,
+        calls
+      ) do
+    HostCore.WasmCloud.Native.start_profiling(actor_resource, path, calls)
+  end
+
+  @doc """
+  Stops profiling the actor and writes the profile, returning how many calls were profiled and how many
+  samples were taken. Calls still running are not included
+  """
+  @spec stop_profiling(HostCore.WasmCloud.Runtime.ActorReference.t()) ::
+          {:ok, %{path: binary(), calls: non_neg_integer(), samples: non_neg_integer()}}
+          | {:error, binary()}
+  def stop_profiling(%HostCore.WasmCloud.Runtime.ActorReference{resource: actor_resource}) do
+    case HostCore.WasmCloud.Native.stop_profiling(actor_resource) do
+      {:error, err} -> {:error, err}
+      summary -> {:ok, summary}
+    end
+  end
+
+  @doc """
+  Stops the runtime from accepting new actors or calls and waits up to `timeout_ms` for the calls already
+  in flight to finish. Afterwards, any host callbacks still waiting on Elixir are failed and no new ones
+  are accepted, so the runtime cannot be used again
+  """
+  @spec drain(__MODULE__.t(), non_neg_integer()) :: :ok | {:error, binary()}
+  def drain(%__MODULE__{resource: resource}, timeout_ms) do
+    HostCore.WasmCloud.Native.drain_runtime(resource, timeout_ms)
   end
 
   defimpl Inspect, for: HostCore.WasmCloud.Runtime do
@@ -3,15 +3,126 @@ defmodule HostCore.WasmCloud.Runtime.Config do
   Configures a `WasmCloud.Runtime`.
 
   ## Options
-    * `:placeholder` - holds a place
+    * `:host_id` - public key of the host that owns the runtime
+    * `:callback_timeout_ms` - how long an actor's host call may wait for the host to respond before
+      failing. Defaults to 30 seconds
+    * `:log_queue_capacity` - how many actor log lines may be queued for delivery to the host
+    * `:log_overflow` - what to do with actor log lines when the queue is full, either `:drop` them or
+      `:block` the actor until there is room
+    * `:numbergen_seed` - when set, the builtin numbergen capability hands each actor a reproducible
+      sequence derived from this seed and the actor's public key instead of real randomness. Intended
+      for tests and replaying recorded invocations
+    * `:builtin_keyvalue` - serve `wasmcloud:keyvalue` host calls from an in-memory store inside the
+      runtime rather than a capability provider. Meant for development and tests, nothing is persisted
+      and no link definition is needed. The builtin serves the contract on every link name and takes
+      precedence over link definitions, so a keyvalue provider linked to an actor is never called while
+      it is enabled. All actors on the runtime share a single keyspace, so actors using the same keys
+      see each other's values. Actors still need the `wasmcloud:keyvalue` capability claim. Defaults
+      to `false`
+    * `:local_actor_calls` - deliver calls between actors registered with the runtime directly inside
+      the runtime instead of over the lattice. Each such call is still signed with the cluster seed,
+      validated against `:cluster_issuers` and the calling actor's claims expiry like any other
+      invocation, and reported back to the host, which publishes the usual invocation events. Policy is
+      not evaluated for these calls, so the host never enables them when a policy service is configured.
+      Defaults to `false`
+    * `:cluster_seed` - seed of the cluster key used to sign direct local actor calls
+    * `:cluster_issuers` - public keys of the cluster issuers whose invocations are accepted, against
+      which direct local actor calls are validated
+    * `:module_cache_dir` - directory in which compiled actor modules are kept across restarts, so that
+      starting an actor whose module was compiled before skips compilation. Artifacts from another
+      runtime version or compiled under different engine settings are ignored and recompiled. When
+      `nil`, or if the directory can't be used (e.g. its path isn't valid UTF-8), every module is
+      compiled from its bytes
+    * `:wasi_sandbox_dir` - the directory under which actors may be given read-only WASI preopens, see
+      `HostCore.WasmCloud.Runtime.WasiConfig`. When `nil`, no actor can be given access to the filesystem
+    * `:allowed_imports` - when set, a list of `HostCore.WasmCloud.Runtime.ImportRule`s outside of which
+      actor modules may not import anything. `nil` allows every import
+    * `:denied_imports` - `HostCore.WasmCloud.Runtime.ImportRule`s for imports actor modules may not have,
+      taking precedence over `:allowed_imports`. Starting an actor with a disallowed import fails with an
+      error listing all of them
+    * `:opt_level` - how hard the engine optimises compiled actors, one of `:none`, `:speed` or
+      `:speed_and_size`. Defaults to `:speed`
+    * `:parallel_compilation` - compile the functions of a module on several threads. Defaults to `true`
+    * `:pooling_allocator` - preallocate a pool of instance slots rather than allocating each instance on
+      demand, trading memory reserved up front for faster instantiation. Defaults to `false`
+    * `:max_wasm_stack` - bytes of native stack an actor call may use before trapping, at most 2 MiB.
+      `nil` keeps the engine's default
+    * `:wasm_proposals` - the exact set of WebAssembly proposals to enable, from `:simd`, `:relaxed_simd`,
+      `:bulk_memory`, `:reference_types`, `:multi_value`, `:threads`, `:multi_memory` and `:memory64`.
+      `nil` keeps the engine's defaults. The component model is always enabled, so that actors may be
+      components as well as core modules
+    * `:guest_profiling` - allow the actors started on the runtime to be profiled with
+      `HostCore.WasmCloud.Runtime.start_profiling/3`. This has the engine check for interruptions
+      throughout compiled code, which makes every actor a little slower. Defaults to `false`
+    * `:interruptible_calls` - interrupt cancelled (or timed out) actor calls even while the actor runs
+      its own code, e.g. a loop that never calls the host. Without it, such a call keeps running until it
+      next yields to the host. Like `:guest_profiling`, this has the engine check for interruptions
+      throughout compiled code, which makes every actor a little slower. Defaults to `false`
+    * `:fuel_metering` - report the fuel each actor call burns in its metrics, a measure of the work the
+      actor did that doesn't depend on the machine. This has the engine account for fuel throughout
+      compiled code, which makes every actor noticeably slower. Defaults to `false`
+
+  Inconsistent engine settings, such as `:reference_types` without `:bulk_memory`, fail runtime creation.
+  The configuration the engine ends up with is reported by `HostCore.WasmCloud.Runtime.engine_config/1`
 
   ## Example
       iex> _config = %HostCore.WasmCloud.Runtime.Config{}
   """
 
-  defstruct host_id: ""
+  defstruct host_id: "",
+            callback_timeout_ms: 30_000,
+            log_queue_capacity: 1024,
+            log_overflow: :drop,
+            numbergen_seed: nil,
+            builtin_keyvalue: false,
+            local_actor_calls: false,
+            cluster_seed: "",
+            cluster_issuers: [],
+            module_cache_dir: nil,
+            wasi_sandbox_dir: nil,
+            allowed_imports: nil,
+            denied_imports: [],
+            opt_level: :speed,
+            parallel_compilation: true,
+            pooling_allocator: false,
+            max_wasm_stack: nil,
+            wasm_proposals: nil,
+            guest_profiling: false,
+            interruptible_calls: false,
+            fuel_metering: false
+
+  @type opt_level :: :none | :speed | :speed_and_size
+  @type wasm_proposal ::
+          :simd
+          | :relaxed_simd
+          | :bulk_memory
+          | :reference_types
+          | :multi_value
+          | :threads
+          | :multi_memory
+          | :memory64
 
   @type t :: %__MODULE__{
-          host_id: binary()
+          host_id: binary(),
+          callback_timeout_ms: non_neg_integer(),
+          log_queue_capacity: pos_integer(),
+          log_overflow: :drop | :block,
+          numbergen_seed: non_neg_integer() | nil,
+          builtin_keyvalue: boolean(),
+          local_actor_calls: boolean(),
+          cluster_seed: binary(),
+          cluster_issuers: [binary()],
+          module_cache_dir: binary() | nil,
+          wasi_sandbox_dir: binary() | nil,
+          allowed_imports: [HostCore.WasmCloud.Runtime.ImportRule.t()] | nil,
+          denied_imports: [HostCore.WasmCloud.Runtime.ImportRule.t()],
+          opt_level: opt_level(),
+          parallel_compilation: boolean(),
+          pooling_allocator: boolean(),
+          max_wasm_stack: pos_integer() | nil,
+          wasm_proposals: [wasm_proposal()] | nil,
+          guest_profiling: boolean(),
+          interruptible_calls: boolean(),
+          fuel_metering: boolean()
         }
 end
@@ -7,13 +7,17 @@ defmodule HostCore.WasmCloud.Runtime.Server do
   """
   use GenServer
   require Logger
-  require OpenTelemetry.Tracer, as: Tracer
 
   alias HostCore.WasmCloud.Runtime.Config, as: RuntimeConfig
+  alias HostCore.WasmCloud.Runtime.ActorConfig
   alias HostCore.WasmCloud.Runtime.ActorReference
+  alias HostCore.WasmCloud.Runtime.CallContext
 
   import HostCore.WasmCloud.RpcInvocations
 
+  # how long an actor call may run before it is cancelled, unless the caller asks otherwise
+  @default_call_timeout_ms 5_000
+
   @doc """
   Starts this server with the supplied configuration. This configuration corresponds to the configuration
   required by the Rust wasmCloud runtime SDK so it needs to be kept in agreement with the equivalent data
@@ -47,99 +51,281 @@ defmodule HostCore.WasmCloud.Runtime.Server do
   def init(%RuntimeConfig{} = config) do
     {:ok, runtime} = HostCore.WasmCloud.Runtime.new(config)
 
-    {:ok, {runtime, config}}
+    # in-flight actor calls, keyed by the monitor on the process awaiting each one, along with the
+    # monitor of each call keyed by its `from` so that a completed call is found without a scan
+    {:ok, {runtime, config, %{by_monitor: %{}, by_from: %{}}}}
   end
 
   def version(pid) do
     GenServer.call(pid, :get_version)
   end
 
-  @spec precompile_actor(pid :: pid(), bytes :: binary()) ::
+  @doc """
+  Returns the effective configuration of the runtime's engine, see
+  `HostCore.WasmCloud.Runtime.engine_config/1`
+  """
+  def engine_config(pid) do
+    GenServer.call(pid, :get_engine_config)
+  end
+
+  @doc """
+  Returns the delivered/dropped/backpressured counters of the runtime's actor log queue
+  """
+  def actor_log_stats(pid) do
+    GenServer.call(pid, :get_actor_log_stats)
+  end
+
+  @doc """
+  Returns load statistics for the runtime, see `HostCore.WasmCloud.Runtime.stats/1`
+  """
+  def stats(pid) do
+    GenServer.call(pid, :get_stats)
+  end
+
+  @doc """
+  Describes the runtime's compiled-module cache, see `HostCore.WasmCloud.Runtime.module_cache_stats/1`
+  """
+  def module_cache_stats(pid) do
+    GenServer.call(pid, :get_module_cache_stats)
+  end
+
+  @doc """
+  Empties the runtime's compiled-module cache, see `HostCore.WasmCloud.Runtime.purge_module_cache/1`
+  """
+  @spec purge_module_cache(pid :: pid()) :: {:ok, non_neg_integer()} | {:error, binary()}
+  def purge_module_cache(pid) do
+    GenServer.call(pid, :purge_module_cache)
+  end
+
+  @doc """
+  Reseeds the builtin numbergen capability, see `HostCore.WasmCloud.Runtime.reseed_numbergen/2`
+  """
+  @spec reseed_numbergen(pid :: pid(), seed :: non_neg_integer() | nil) :: :ok
+  def reseed_numbergen(pid, seed) do
+    GenServer.call(pid, {:reseed_numbergen, seed})
+  end
+
+  @spec precompile_actor(pid :: pid(), bytes :: binary(), actor_config :: ActorConfig.t()) ::
           {:ok, ActorReference.t()} | {:error, binary()}
-  def precompile_actor(pid, bytes) do
-    GenServer.call(pid, {:precompile_actor, bytes})
+  def precompile_actor(pid, bytes, actor_config \\ %ActorConfig{}) do
+    GenServer.call(pid, {:precompile_actor, bytes, actor_config})
   end
 
+  @doc """
+  Live updates a running actor, see `HostCore.WasmCloud.Runtime.update_actor/3`
+  """
+  @spec update_actor(pid :: pid(), actor_reference :: ActorReference.t(), bytes :: binary()) ::
+          :ok | {:error, binary()}
+  def update_actor(pid, actor_reference, bytes) do
+    GenServer.call(pid, {:update_actor, actor_reference, bytes})
+  end
+
+  @doc """
+  Invokes an operation on an actor, waiting up to `timeout_ms` for it to complete. A call that takes
+  longer is cancelled and `{:error, :timeout}` is returned. A call whose caller goes away before it
+  completes is cancelled as well. Whether a cancelled actor is stopped right away depends on the
+  runtime's `interruptible_calls` setting, see `HostCore.WasmCloud.Runtime.cancel_actor_call/1`
+  """
   @spec invoke_actor(
           pid :: pid(),
           actor_reference :: ActorReference.t(),
           operation :: binary(),
           payload :: binary(),
-          call_context :: binary()
-        ) :: {:ok, binary()} | {:error, binary()}
-  def invoke_actor(pid, actor_reference, operation, payload, call_context) do
-    GenServer.call(pid, {:invoke_actor, actor_reference, operation, payload, call_context})
-  end
-
-  # calls into the NIF to invoke the given operation on the indicated actor instance
-  @impl true
-  def handle_call({:invoke_actor, actor_reference, operation, payload, call_context}, from, state) do
-    :ok =
-      HostCore.WasmCloud.Runtime.call_actor(
+          call_context :: binary(),
+          invocation_id :: binary(),
+          timeout_ms :: pos_integer()
+        ) :: {:ok, binary(), HostCore.WasmCloud.Runtime.call_metrics()}
+          | {:error, binary()}
+          | {:error, :busy}
+          | {:error, :timeout}
+  def invoke_actor(
+        pid,
         actor_reference,
         operation,
         payload,
         call_context,
-        from
-      )
+        invocation_id,
+        timeout_ms \\ @default_call_timeout_ms
+      ) do
+    # the server replies once the call has been cancelled, so give it some slack over the call timeout
+    GenServer.call(
+      pid,
+      {:invoke_actor, actor_reference, operation, payload, call_context, invocation_id, timeout_ms},
+      timeout_ms + 5_000
+    )
+  end
+
+  @doc """
+  Stops the runtime from accepting new actors or calls, waits up to `timeout_ms` for in-flight calls to
+  finish and then fails any host callbacks still outstanding. The runtime cannot be used afterwards
+  """
+  @spec drain(pid :: pid(), timeout_ms :: non_neg_integer()) :: :ok | {:error, binary()}
+  def drain(pid, timeout_ms) do
+    GenServer.call(pid, {:drain, timeout_ms}, timeout_ms + 5_000)
+  end
+
+  # calls into the NIF to invoke the given operation on the indicated actor instance. The caller
+  # is monitored so that the invocation can be cancelled if it goes away before the actor replies,
+  # and the invocation is cancelled as well once it runs out of time
+  @impl true
+  def handle_call(
+        {:invoke_actor, actor_reference, operation, payload, call_context, invocation_id,
+         timeout_ms},
+        {caller, _tag} = from,
+        {runtime, config, calls} = state
+      ) do
+    case HostCore.WasmCloud.Runtime.call_actor(
+           actor_reference,
+           operation,
+           payload,
+           call_context,
+           invocation_id,
+           from
+         ) do
+      {:ok, call_ref} ->
+        monitor_ref = Process.monitor(caller)
+        timer = Process.send_after(self(), {:actor_call_timeout, monitor_ref}, timeout_ms)
+
+        calls = %{
+          by_monitor: Map.put(calls.by_monitor, monitor_ref, {from, call_ref, timer}),
+          by_from: Map.put(calls.by_from, from, monitor_ref)
+        }
+
+        {:noreply, {runtime, config, calls}}
+
+      {:error, _reason} = error ->
+        {:reply, error, state}
+    end
+  end
+
+  # draining waits on in-flight calls, which in turn may need this server to dispatch their host
+  # callbacks, so the wait happens outside of this process
+  @impl true
+  def handle_call({:drain, timeout_ms}, from, {runtime, _config, _calls} = state) do
+    Task.Supervisor.start_child(RuntimeCallSupervisor, fn ->
+      GenServer.reply(from, HostCore.WasmCloud.Runtime.drain(runtime, timeout_ms))
+    end)
 
     {:noreply, state}
   end
 
   # calls into the NIF to call into the runtime instance to create a new actor
   @impl true
-  def handle_call({:precompile_actor, bytes}, _from, {runtime, _config} = state) do
-    {:reply, HostCore.WasmCloud.Runtime.start_actor(runtime, bytes), state}
+  def handle_call(
+        {:precompile_actor, bytes, actor_config},
+        _from,
+        {runtime, _config, _calls} = state
+      ) do
+    {:reply, HostCore.WasmCloud.Runtime.start_actor(runtime, bytes, actor_config), state}
   end
 
   @impl true
-  def handle_call(:get_version, _from, {runtime, _config} = state) do
+  def handle_call(
+        {:update_actor, actor_reference, bytes},
+        _from,
+        {runtime, _config, _calls} = state
+      ) do
+    {:reply, HostCore.WasmCloud.Runtime.update_actor(runtime, actor_reference, bytes), state}
+  end
+
+  @impl true
+  def handle_call(:get_version, _from, {runtime, _config, _calls} = state) do
     {:reply, HostCore.WasmCloud.Runtime.version(runtime), state}
   end
 
+  @impl true
+  def handle_call(:get_engine_config, _from, {runtime, _config, _calls} = state) do
+    {:reply, HostCore.WasmCloud.Runtime.engine_config(runtime), state}
+  end
+
+  @impl true
+  def handle_call(:get_actor_log_stats, _from, {runtime, _config, _calls} = state) do
+    {:reply, HostCore.WasmCloud.Runtime.actor_log_stats(runtime), state}
+  end
+
+  @impl true
+  def handle_call(:get_stats, _from, {runtime, _config, _calls} = state) do
+    {:reply, HostCore.WasmCloud.Runtime.stats(runtime), state}
+  end
+
+  @impl true
+  def handle_call(:get_module_cache_stats, _from, {runtime, _config, _calls} = state) do
+    {:reply, HostCore.WasmCloud.Runtime.module_cache_stats(runtime), state}
+  end
+
+  @impl true
+  def handle_call(:purge_module_cache, _from, {runtime, _config, _calls} = state) do
+    {:reply, HostCore.WasmCloud.Runtime.purge_module_cache(runtime), state}
+  end
+
+  @impl true
+  def handle_call({:reseed_numbergen, seed}, _from, {runtime, _config, _calls} = state) do
+    {:reply, HostCore.WasmCloud.Runtime.reseed_numbergen(runtime, seed), state}
+  end
+
   # this gets called from inside the NIF to indicate that a function call has completed
   # the `from` here is the same from (via passthrough) that came from the
-  # GenServer call to `:invoke_actor`
+  # GenServer call to `:invoke_actor`. A call that was abandoned (its caller went away or it timed
+  # out) has already been answered, if anyone was left to answer, so its late result is dropped
   @impl true
-  def handle_info({:returned_function_call, result, from}, state) do
-    # the binary comes out of the NIF as  {:ok, vec<u8>} or {:error, vec<u8>}
-    # so we need to turn the second element from a vec<u8> into a << ...>> binary
-    {code, bindata} = result
-
-    response_data =
-      cond do
-        is_nil(bindata) ->
-          <<>>
+  def handle_info({:returned_function_call, {:error, :cancelled}, from}, state) do
+    {:noreply, reply_to_call(state, from, {:error, :cancelled})}
+  end
 
-        is_binary(bindata) && byte_size(bindata) == 0 ->
-          <<>>
+  @impl true
+  def handle_info({:returned_function_call, {:ok, bindata, metrics}, from}, state) do
+    {:noreply, reply_to_call(state, from, {:ok, to_binary(bindata), metrics})}
+  end
 
-        true ->
-          IO.iodata_to_binary(bindata)
-      end
+  @impl true
+  def handle_info({:returned_function_call, {:error, msg}, from}, state) do
+    {:noreply, reply_to_call(state, from, {:error, to_binary(msg)})}
+  end
 
-    GenServer.reply(from, {code, response_data})
+  # the process waiting on an actor call went away (e.g. it was shut down), so there is no one left
+  # to reply to and the invocation can be abandoned
+  @impl true
+  def handle_info({:DOWN, monitor_ref, :process, _pid, _reason}, {runtime, config, calls} = state) do
+    case pop_call(calls, monitor_ref) do
+      {nil, _calls} ->
+        {:noreply, state}
+
+      {{_from, call_ref, timer}, calls} ->
+        Process.cancel_timer(timer)
+        _ = HostCore.WasmCloud.Runtime.cancel_actor_call(call_ref)
+        {:noreply, {runtime, config, calls}}
+    end
+  end
 
-    {:noreply, state}
+  # the actor call ran out of time, so it is abandoned and its caller told so right away
+  @impl true
+  def handle_info({:actor_call_timeout, monitor_ref}, {runtime, config, calls} = state) do
+    case pop_call(calls, monitor_ref) do
+      {nil, _calls} ->
+        {:noreply, state}
+
+      {{from, call_ref, _timer}, calls} ->
+        Process.demonitor(monitor_ref, [:flush])
+        _ = HostCore.WasmCloud.Runtime.cancel_actor_call(call_ref)
+        GenServer.reply(from, {:error, :timeout})
+        {:noreply, {runtime, config, calls}}
+    end
   end
 
   @impl true
   def handle_info(
         {:invoke_callback, claims, {binding, namespace, operation}, payload, call_context, token},
-        {_runtime, config} = state
+        {_runtime, config, _calls} = state
       ) do
     Task.Supervisor.start_child(RuntimeCallSupervisor, fn ->
       # This callback is invoked by the wasmcloud::Runtime's host call handler
       payload = payload |> IO.iodata_to_binary()
 
-      # Convert the `call_context` list of bytes to the `span_ctx` term
-      with true <- is_list(call_context),
-           ctx_binary <- :erlang.list_to_binary(call_context),
-           true <- is_binary(ctx_binary),
-           span_ctx <- :erlang.binary_to_term(ctx_binary) do
-        Tracer.set_current_span(span_ctx)
-      else
-        # If the call context didn't contain a span, that's ok and we silently ignore
+      # The NIF decodes the context the actor was called with, `nil` if it wasn't given one
+      case call_context do
+        %CallContext{trace_context: trace_context} when map_size(trace_context) > 0 ->
+          :otel_propagator_text_map.extract(Map.to_list(trace_context))
+
         _ ->
           :ok
       end
@@ -158,24 +344,95 @@ defmodule HostCore.WasmCloud.Runtime.Server do
     {:noreply, state}
   end
 
+  # calls delivered directly between actors on this runtime never go through `rpc_invoke`, so the
+  # runtime reports each one to be published like any other invocation
   @impl true
-  def handle_info({:perform_actor_log, claims, level, text, token}, state) do
-    text = "[#{claims.public_key}] #{text}"
-    actor = claims.public_key
+  def handle_info({:local_actor_invocation, audit}, {_runtime, config, _calls} = state) do
+    case HostCore.Vhost.VirtualHost.config(config.host_id) do
+      nil ->
+        :ok
+
+      host_config ->
+        res =
+          case audit.error do
+            nil -> {:ok, audit.id}
+            error -> {:error, error}
+          end
+
+        Task.Supervisor.start_child(InvocationTaskSupervisor, fn ->
+          publish_invocation_result(
+            audit.origin,
+            nil,
+            nil,
+            audit.operation,
+            audit.bytes,
+            :actor,
+            audit.target,
+            res,
+            host_config.lattice_prefix,
+            config.host_id
+          )
+        end)
+    end
+
+    {:noreply, state}
+  end
+
+  # actor logs are fire-and-forget, the NIF doesn't wait for an acknowledgement. Elixir's Logger has
+  # no trace level, so trace lines are logged at debug and tagged with their original level
+  @impl true
+  def handle_info({:perform_actor_log, level, text, log_metadata}, state) do
+    text = "[#{log_metadata.actor_id}] #{text}"
+
+    metadata =
+      [
+        actor_id: log_metadata.actor_id,
+        call_alias: log_metadata.call_alias,
+        invocation_id: log_metadata.invocation_id,
+        operation: log_metadata.operation
+      ]
+      |> Enum.reject(fn {_k, v} -> is_nil(v) end)
 
     case level do
-      "error" -> Logger.error(text, actor_id: actor)
-      "info" -> Logger.info(text, actor_id: actor)
-      "warn" -> Logger.warn(text, actor_id: actor)
-      "debug" -> Logger.debug(text, actor_id: actor)
-      _ -> Logger.debug(text, actor_id: actor)
+      :error -> Logger.error(text, metadata)
+      :info -> Logger.info(text, metadata)
+      :warn -> Logger.warn(text, metadata)
+      :debug -> Logger.debug(text, metadata)
+      :trace -> Logger.debug(text, [{:actor_log_level, "trace"} | metadata])
+      _ -> Logger.debug(text, metadata)
     end
 
-    :ok = HostCore.WasmCloud.Native.instance_receive_callback_result(token, true, <<>>)
-
     {:noreply, state}
   end
 
+  # the binary comes out of the NIF as a vec<u8> (or nil), so we need to turn it into a << ...>> binary
+  defp to_binary(nil), do: <<>>
+  defp to_binary(bindata), do: IO.iodata_to_binary(bindata)
+
+  defp reply_to_call({runtime, config, calls} = state, from, reply) do
+    case Map.fetch(calls.by_from, from) do
+      :error ->
+        state
+
+      {:ok, monitor_ref} ->
+        {{_from, _call_ref, timer}, calls} = pop_call(calls, monitor_ref)
+        Process.demonitor(monitor_ref, [:flush])
+        Process.cancel_timer(timer)
+        GenServer.reply(from, reply)
+        {runtime, config, calls}
+    end
+  end
+
+  defp pop_call(calls, monitor_ref) do
+    case Map.pop(calls.by_monitor, monitor_ref) do
+      {nil, _by_monitor} ->
+        {nil, calls}
+
+      {{from, _call_ref, _timer} = call, by_monitor} ->
+        {call, %{by_monitor: by_monitor, by_from: Map.delete(calls.by_from, from)}}
+    end
+  end
+
   defp do_invocation(claims, binding, namespace, operation, payload, rt_config) do
     host_config = HostCore.Vhost.VirtualHost.config(rt_config.host_id)
     actor = claims.public_key
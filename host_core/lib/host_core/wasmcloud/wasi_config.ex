@@ -0,0 +1,27 @@
+defmodule HostCore.WasmCloud.Runtime.WasiConfig do
+  @moduledoc ~S"""
+  The WASI environment of an actor started on a `WasmCloud.Runtime`. Anything the actor writes to stdout
+  or stderr is forwarded to the actor log at `:info` and `:warn` level respectively, tagged with the actor's
+  identity and the invocation it was serving.
+
+  ## Options
+    * `:env` - environment variables visible to the actor
+    * `:args` - command line arguments visible to the actor
+    * `:preopens` - directories the actor may read, given relative to the runtime's `:wasi_sandbox_dir`
+      and mounted read-only at the same path from the guest's root. Starting the actor fails if one of
+      them doesn't exist or lies outside of the sandbox
+
+  ## Example
+      iex> _config = %HostCore.WasmCloud.Runtime.WasiConfig{env: %{"MODE" => "dev"}, preopens: ["assets"]}
+  """
+
+  defstruct env: %{},
+            args: [],
+            preopens: []
+
+  @type t :: %__MODULE__{
+          env: %{binary() => binary()},
+          args: [binary()],
+          preopens: [binary()]
+        }
+end
@@ -0,0 +1,150 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use log::warn;
+use rustler::{Encoder, LocalPid};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use wasmcloud::logging;
+
+use crate::{atoms, environment::send_to_pid};
+
+/// What to do with a log line written by an actor when the log queue is full
+#[derive(Debug, Copy, Clone, NifUnitEnum)]
+pub enum LogOverflow {
+    /// Discard the line and count it as dropped
+    Drop,
+    /// Make the actor wait until there is room in the queue
+    Block,
+}
+
+/// Severity of an actor log line. Unlike the wasmbus logging interface, the native log path has a
+/// trace level
+#[derive(Debug, Copy, Clone, NifUnitEnum)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<logging::Level> for LogLevel {
+    fn from(level: logging::Level) -> Self {
+        match level {
+            logging::Level::Debug => LogLevel::Debug,
+            logging::Level::Info => LogLevel::Info,
+            logging::Level::Warn => LogLevel::Warn,
+            logging::Level::Error => LogLevel::Error,
+        }
+    }
+}
+
+/// Identifies the actor, and the call it was serving, that wrote a log line. These are surfaced to
+/// Elixir as logger metadata so that structured logging can emit them as fields
+#[derive(NifMap, Clone, Default)]
+pub struct ActorLogMetadata {
+    pub actor_id: String,
+    pub call_alias: Option<String>,
+    pub invocation_id: Option<String>,
+    pub operation: Option<String>,
+}
+
+/// A log line written by an actor, waiting to be forwarded to the Elixir host
+pub(crate) struct ActorLog {
+    pub level: LogLevel,
+    pub text: String,
+    pub metadata: ActorLogMetadata,
+}
+
+/// Counters describing how the actor log queue of a runtime has been used
+#[derive(NifMap, Default)]
+pub struct LogQueueStats {
+    /// Lines handed to the Elixir host
+    pub delivered: u64,
+    /// Lines discarded because the queue was full or the host was gone
+    pub dropped: u64,
+    /// Times an actor had to wait for room in the queue
+    pub backpressured: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    backpressured: AtomicU64,
+}
+
+/// A bounded queue of actor log lines drained by a background task, so that writing a log never
+/// waits on a round trip to the BEAM
+pub(crate) struct LogQueue {
+    sender: mpsc::Sender<ActorLog>,
+    overflow: LogOverflow,
+    counters: Arc<Counters>,
+}
+
+impl LogQueue {
+    /// Creates the queue and spawns the task forwarding its contents to `pid` as `perform_actor_log`
+    /// messages. The task stops once the queue is dropped
+    pub(crate) fn start(pid: LocalPid, capacity: usize, overflow: LogOverflow) -> LogQueue {
+        let (sender, mut receiver) = mpsc::channel::<ActorLog>(capacity.max(1));
+        let counters = Arc::new(Counters::default());
+
+        let forward_counters = counters.clone();
+        crate::spawn(async move {
+            while let Some(ActorLog {
+                level,
+                text,
+                metadata,
+            }) = receiver.recv().await
+            {
+                let delivered = send_to_pid(&pid, |env| {
+                    (atoms::perform_actor_log(), level, text, metadata).encode(env)
+                });
+                if delivered {
+                    forward_counters.delivered.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    forward_counters.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+
+        LogQueue {
+            sender,
+            overflow,
+            counters,
+        }
+    }
+
+    /// Queues a log line for the Elixir host, applying the overflow policy if the queue is full
+    pub(crate) async fn push(&self, log: ActorLog) {
+        let log = match self.sender.try_send(log) {
+            Ok(()) => return,
+            Err(TrySendError::Full(log)) => log,
+            Err(TrySendError::Closed(_)) => {
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        };
+
+        match self.overflow {
+            LogOverflow::Drop => {
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            LogOverflow::Block => {
+                self.counters.backpressured.fetch_add(1, Ordering::Relaxed);
+                if self.sender.send(log).await.is_err() {
+                    warn!("actor log queue closed while waiting for room, dropping log line");
+                    self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn stats(&self) -> LogQueueStats {
+        LogQueueStats {
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            backpressured: self.counters.backpressured.load(Ordering::Relaxed),
+        }
+    }
+}
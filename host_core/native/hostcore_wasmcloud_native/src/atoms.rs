@@ -24,4 +24,15 @@ rustler::atoms! {
     invoke_callback,
 
     perform_actor_log,
+    local_actor_invocation,
+
+    // actor call cancellation
+    cancelled,
+    already_completed,
+
+    // actor concurrency limits
+    busy,
+
+    // actor health checks
+    actor_health,
 }
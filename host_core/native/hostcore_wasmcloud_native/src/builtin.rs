@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use wascap::jwt;
+
+/// A capability served in-process by the runtime. Host calls an actor makes to a contract ID and
+/// link name with a registered builtin are handed to it directly instead of making the round trip
+/// through the Elixir host. The runtime doesn't know about link definitions, so a builtin takes
+/// precedence over any provider the actor is linked to for the same contract and link name
+#[async_trait]
+pub(crate) trait Builtin: Send + Sync {
+    /// Performs an operation on behalf of an actor, taking and returning message pack payloads
+    /// exactly as a capability provider would
+    async fn handle(
+        &self,
+        claims: &jwt::Claims<jwt::Actor>,
+        operation: &str,
+        payload: &[u8],
+    ) -> anyhow::Result<Vec<u8>>;
+}
+
+/// The builtins registered with a runtime, keyed by contract ID and link name. A builtin registered
+/// without a link name serves its contract on every link that doesn't have one of its own, shadowing
+/// the providers linked to actors for that contract on all of them. Builtins are registered when the
+/// runtime is created, from its configuration, and stay for the runtime's lifetime
+#[derive(Default)]
+pub(crate) struct BuiltinRegistry {
+    builtins: RwLock<HashMap<(String, Option<String>), Arc<dyn Builtin>>>,
+}
+
+impl BuiltinRegistry {
+    /// Registers a builtin, replacing any previously registered for the same contract and link
+    pub(crate) fn register(
+        &self,
+        contract_id: impl Into<String>,
+        link_name: Option<String>,
+        builtin: Arc<dyn Builtin>,
+    ) {
+        self.builtins
+            .write()
+            .unwrap()
+            .insert((contract_id.into(), link_name), builtin);
+    }
+
+    /// Finds the builtin serving a contract on a link, if any
+    pub(crate) fn find(&self, contract_id: &str, link_name: &str) -> Option<Arc<dyn Builtin>> {
+        let builtins = self.builtins.read().unwrap();
+        builtins
+            .get(&(contract_id.to_string(), Some(link_name.to_string())))
+            .or_else(|| builtins.get(&(contract_id.to_string(), None)))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use wascap::jwt;
+
+    use super::{Builtin, BuiltinRegistry};
+
+    struct Noop;
+
+    #[async_trait]
+    impl Builtin for Noop {
+        async fn handle(
+            &self,
+            _claims: &jwt::Claims<jwt::Actor>,
+            _operation: &str,
+            _payload: &[u8],
+        ) -> anyhow::Result<Vec<u8>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn same(found: Option<Arc<dyn Builtin>>, expected: &Arc<dyn Builtin>) -> bool {
+        found.map_or(false, |found| {
+            Arc::as_ptr(&found) as *const () == Arc::as_ptr(expected) as *const ()
+        })
+    }
+
+    #[test]
+    fn link_specific_builtins_take_precedence() {
+        let any_link: Arc<dyn Builtin> = Arc::new(Noop);
+        let cache_link: Arc<dyn Builtin> = Arc::new(Noop);
+
+        let registry = BuiltinRegistry::default();
+        registry.register("wasmcloud:keyvalue", None, any_link.clone());
+        registry.register(
+            "wasmcloud:keyvalue",
+            Some("cache".to_string()),
+            cache_link.clone(),
+        );
+
+        assert!(same(
+            registry.find("wasmcloud:keyvalue", "default"),
+            &any_link
+        ));
+        assert!(same(
+            registry.find("wasmcloud:keyvalue", "cache"),
+            &cache_link
+        ));
+        assert!(registry.find("wasmcloud:messaging", "default").is_none());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::inv;
+
+/// Written as the first byte of every encoded call context, so that the layout can change without
+/// older contexts being misread
+const CALL_CONTEXT_VERSION: u8 = 1;
+
+/// What the host knows about the call an actor is handling, passed through the actor and back to the
+/// host with each of its host calls
+#[derive(NifStruct, Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[module = "HostCore.WasmCloud.Runtime.CallContext"]
+pub struct CallContext {
+    /// The propagated trace context, e.g. the W3C `traceparent` and `tracestate` headers
+    #[serde(default)]
+    pub trace_context: HashMap<String, String>,
+    /// Unix time in milliseconds after which the caller no longer waits for the result
+    #[serde(default)]
+    pub deadline_ms: Option<u64>,
+    #[serde(default)]
+    pub caller: Option<CallerEntity>,
+    #[serde(default)]
+    pub invocation_id: Option<String>,
+}
+
+/// The actor or provider that made a call. Actors have an empty contract ID and link name
+#[derive(NifMap, Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallerEntity {
+    pub public_key: String,
+    #[serde(default)]
+    pub contract_id: String,
+    #[serde(default)]
+    pub link_name: String,
+}
+
+impl CallContext {
+    pub(crate) fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = vec![CALL_CONTEXT_VERSION];
+        bytes.extend(inv::serialize(self).map_err(|e| anyhow!("{e}"))?);
+        Ok(bytes)
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> anyhow::Result<CallContext> {
+        match bytes.split_first() {
+            None => bail!("call context is empty"),
+            Some((&CALL_CONTEXT_VERSION, body)) => {
+                inv::deserialize(body).map_err(|e| anyhow!("invalid call context: {e}"))
+            }
+            Some((version, _)) => bail!("unsupported call context version {version}"),
+        }
+    }
+
+    /// Decodes the context an actor passed along with a host call. Actors may be called without a
+    /// context or with one from before contexts were versioned, neither of which is an error
+    pub(crate) fn from_host_call(bytes: &Option<Vec<u8>>) -> Option<CallContext> {
+        bytes
+            .as_deref()
+            .filter(|bytes| !bytes.is_empty())
+            .and_then(|bytes| CallContext::decode(bytes).ok())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CallContext, CallerEntity};
+
+    #[test]
+    fn round_trips_through_its_encoding() {
+        let context = CallContext {
+            trace_context: [(
+                "traceparent".to_string(),
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string(),
+            )]
+            .into(),
+            deadline_ms: Some(1_700_000_000_000),
+            caller: Some(CallerEntity {
+                public_key: "MCALLER".to_string(),
+                ..Default::default()
+            }),
+            invocation_id: Some("inv-1".to_string()),
+        };
+        let bytes = context.encode().unwrap();
+        assert_eq!(bytes[0], 1);
+        assert_eq!(CallContext::decode(&bytes).unwrap(), context);
+    }
+
+    #[test]
+    fn unversioned_contexts_are_not_misread() {
+        assert!(CallContext::decode(&[]).is_err());
+        assert!(CallContext::decode(&[2, 0x80]).is_err());
+        // an Erlang external term, as contexts were before they were versioned
+        assert!(CallContext::decode(&[131, 100, 0, 9]).is_err());
+        assert_eq!(
+            CallContext::from_host_call(&Some(vec![131, 100, 0, 9])),
+            None
+        );
+        assert_eq!(CallContext::from_host_call(&None), None);
+    }
+}
@@ -0,0 +1,27 @@
+const WASM_MAGIC: &[u8] = b"\0asm";
+/// Size of the magic number followed by the version and layer fields
+const PREAMBLE_LEN: usize = 8;
+
+/// Whether the bytes are a WebAssembly component rather than a core module. Both share the magic
+/// number, the layer field that follows the version tells them apart. Claims are read from either
+/// with `wascap::wasm::extract_claims`, which signs and hashes components the same way as modules
+pub(crate) fn is_component(bytes: &[u8]) -> bool {
+    bytes.len() >= PREAMBLE_LEN
+        && &bytes[..4] == WASM_MAGIC
+        && u16::from_le_bytes([bytes[6], bytes[7]]) == 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::is_component;
+
+    const COMPONENT_PREAMBLE: &[u8] = b"\0asm\x0d\0\x01\0";
+    const MODULE_PREAMBLE: &[u8] = b"\0asm\x01\0\0\0";
+
+    #[test]
+    fn tells_components_from_modules() {
+        assert!(is_component(COMPONENT_PREAMBLE));
+        assert!(!is_component(MODULE_PREAMBLE));
+        assert!(!is_component(b"\0asm"));
+    }
+}
@@ -0,0 +1,276 @@
+use anyhow::bail;
+use data_encoding::HEXLOWER;
+use ring::digest::{digest, SHA256};
+use wasmtime::{Config as EngineConfig, InstanceAllocationStrategy};
+
+/// Stack available to a call running on the engine's async fibers, which bounds `max_wasm_stack`
+const ASYNC_STACK_SIZE: usize = 2 << 20;
+/// The engine's own default
+const DEFAULT_MAX_WASM_STACK: usize = 512 * 1024;
+/// The proposals the engine enables unless told otherwise
+const DEFAULT_PROPOSALS: [WasmProposal; 4] = [
+    WasmProposal::Simd,
+    WasmProposal::BulkMemory,
+    WasmProposal::ReferenceTypes,
+    WasmProposal::MultiValue,
+];
+
+#[derive(NifUnitEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptLevel {
+    None,
+    Speed,
+    SpeedAndSize,
+}
+
+/// WebAssembly proposals that can be switched on or off for the actors of a runtime
+#[derive(NifUnitEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WasmProposal {
+    Simd,
+    RelaxedSimd,
+    BulkMemory,
+    ReferenceTypes,
+    MultiValue,
+    Threads,
+    MultiMemory,
+    Memory64,
+}
+
+/// How a runtime's engine compiles and runs actors, as requested in the runtime's configuration
+pub(crate) struct EngineSettings {
+    pub opt_level: OptLevel,
+    pub parallel_compilation: bool,
+    pub pooling_allocator: bool,
+    /// `None` keeps the engine's default
+    pub max_wasm_stack: Option<usize>,
+    /// The exact set of proposals to enable, `None` keeps the engine's defaults
+    pub wasm_proposals: Option<Vec<WasmProposal>>,
+    /// Whether actors can be profiled, which instruments compiled code with epoch checks
+    pub guest_profiling: bool,
+    /// Whether cancelled calls are interrupted even while the guest never yields to the host, which
+    /// instruments compiled code with epoch checks
+    pub interruptible_calls: bool,
+    /// Whether the fuel burnt by each call is reported, which instruments compiled code with fuel
+    /// accounting
+    pub fuel_metering: bool,
+}
+
+/// The configuration a runtime's engine ended up with, defaults included
+#[derive(NifMap, Debug, Clone)]
+pub struct EffectiveEngineConfig {
+    pub opt_level: OptLevel,
+    pub parallel_compilation: bool,
+    pub pooling_allocator: bool,
+    pub max_wasm_stack: usize,
+    pub wasm_proposals: Vec<WasmProposal>,
+    pub guest_profiling: bool,
+    pub interruptible_calls: bool,
+    pub fuel_metering: bool,
+    /// Where compiled modules are cached, `nil` if the runtime compiles every module from its bytes
+    pub module_cache_dir: Option<String>,
+}
+
+impl EffectiveEngineConfig {
+    /// Identifies the settings that change the code the engine compiles, which are part of the key the
+    /// engine caches compiled artifacts under. Settings that only affect how actors are run, such as
+    /// the allocator, leave it unchanged
+    pub(crate) fn compilation_fingerprint(&self) -> String {
+        let mut proposals: Vec<String> = self
+            .wasm_proposals
+            .iter()
+            .map(|proposal| format!("{proposal:?}"))
+            .collect();
+        proposals.sort();
+        let settings = format!(
+            "opt_level={:?};proposals={};epoch_interruption={};fuel={}",
+            self.opt_level,
+            proposals.join(","),
+            self.guest_profiling || self.interruptible_calls,
+            self.fuel_metering,
+        );
+        HEXLOWER.encode(&digest(&SHA256, settings.as_bytes()).as_ref()[..8])
+    }
+}
+
+impl EngineSettings {
+    /// Checks that the settings make sense together, so that a bad configuration is reported in terms
+    /// of the runtime's options rather than failing somewhere inside the engine
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(stack) = self.max_wasm_stack {
+            if stack == 0 {
+                bail!("max_wasm_stack must be greater than zero");
+            }
+            if stack > ASYNC_STACK_SIZE {
+                bail!(
+                    "max_wasm_stack cannot exceed the {ASYNC_STACK_SIZE} byte stack actors run on"
+                );
+            }
+        }
+        let proposals = self.proposals();
+        for (proposal, required) in [
+            (WasmProposal::ReferenceTypes, WasmProposal::BulkMemory),
+            (WasmProposal::Threads, WasmProposal::BulkMemory),
+            (WasmProposal::RelaxedSimd, WasmProposal::Simd),
+        ] {
+            if proposals.contains(&proposal) && !proposals.contains(&required) {
+                bail!("wasm proposal {proposal:?} requires {required:?} to be enabled");
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether guests have to be set up for the engine's epoch ticks
+    pub(crate) fn epoch_interruption(&self) -> bool {
+        self.guest_profiling || self.interruptible_calls
+    }
+
+    fn proposals(&self) -> Vec<WasmProposal> {
+        self.wasm_proposals
+            .clone()
+            .unwrap_or_else(|| DEFAULT_PROPOSALS.to_vec())
+    }
+
+    /// Validates the settings and applies them to an engine configuration, returning the resulting
+    /// effective configuration
+    pub(crate) fn apply(&self, config: &mut EngineConfig) -> anyhow::Result<EffectiveEngineConfig> {
+        self.validate()?;
+
+        config
+            .cranelift_opt_level(match self.opt_level {
+                OptLevel::None => wasmtime::OptLevel::None,
+                OptLevel::Speed => wasmtime::OptLevel::Speed,
+                OptLevel::SpeedAndSize => wasmtime::OptLevel::SpeedAndSize,
+            })
+            .parallel_compilation(self.parallel_compilation);
+        if self.pooling_allocator {
+            config.allocation_strategy(InstanceAllocationStrategy::pooling());
+        }
+        let max_wasm_stack = self.max_wasm_stack.unwrap_or(DEFAULT_MAX_WASM_STACK);
+        config.max_wasm_stack(max_wasm_stack);
+
+        let proposals = self.proposals();
+        let enabled = |proposal| proposals.contains(&proposal);
+        config
+            .wasm_simd(enabled(WasmProposal::Simd))
+            .wasm_relaxed_simd(enabled(WasmProposal::RelaxedSimd))
+            .wasm_bulk_memory(enabled(WasmProposal::BulkMemory))
+            .wasm_reference_types(enabled(WasmProposal::ReferenceTypes))
+            .wasm_multi_value(enabled(WasmProposal::MultiValue))
+            .wasm_threads(enabled(WasmProposal::Threads))
+            .wasm_multi_memory(enabled(WasmProposal::MultiMemory))
+            .wasm_memory64(enabled(WasmProposal::Memory64))
+            // not optional, actors may be components
+            .wasm_component_model(true)
+            .epoch_interruption(self.epoch_interruption())
+            .consume_fuel(self.fuel_metering);
+
+        Ok(EffectiveEngineConfig {
+            opt_level: self.opt_level,
+            parallel_compilation: self.parallel_compilation,
+            pooling_allocator: self.pooling_allocator,
+            max_wasm_stack,
+            wasm_proposals: proposals,
+            guest_profiling: self.guest_profiling,
+            interruptible_calls: self.interruptible_calls,
+            fuel_metering: self.fuel_metering,
+            module_cache_dir: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EngineSettings, OptLevel, WasmProposal};
+
+    fn settings() -> EngineSettings {
+        EngineSettings {
+            opt_level: OptLevel::Speed,
+            parallel_compilation: true,
+            pooling_allocator: false,
+            max_wasm_stack: None,
+            wasm_proposals: None,
+            guest_profiling: false,
+            interruptible_calls: false,
+            fuel_metering: false,
+        }
+    }
+
+    #[test]
+    fn defaults_are_reported_as_effective() {
+        let effective = settings().apply(&mut wasmtime::Config::default()).unwrap();
+        assert_eq!(effective.max_wasm_stack, 512 * 1024);
+        assert!(effective.wasm_proposals.contains(&WasmProposal::Simd));
+        assert!(!effective.wasm_proposals.contains(&WasmProposal::Threads));
+    }
+
+    #[test]
+    fn fingerprint_follows_settings_that_change_compiled_code() {
+        let fingerprint = |settings: EngineSettings| {
+            settings
+                .apply(&mut wasmtime::Config::default())
+                .unwrap()
+                .compilation_fingerprint()
+        };
+        let default = fingerprint(settings());
+        assert_eq!(
+            default,
+            fingerprint(EngineSettings {
+                pooling_allocator: true,
+                parallel_compilation: false,
+                ..settings()
+            })
+        );
+        assert_eq!(
+            default,
+            fingerprint(EngineSettings {
+                wasm_proposals: Some(vec![
+                    WasmProposal::MultiValue,
+                    WasmProposal::ReferenceTypes,
+                    WasmProposal::BulkMemory,
+                    WasmProposal::Simd,
+                ]),
+                ..settings()
+            })
+        );
+        for changed in [
+            EngineSettings {
+                opt_level: OptLevel::None,
+                ..settings()
+            },
+            EngineSettings {
+                interruptible_calls: true,
+                ..settings()
+            },
+            EngineSettings {
+                fuel_metering: true,
+                ..settings()
+            },
+        ] {
+            assert_ne!(default, fingerprint(changed));
+        }
+    }
+
+    #[test]
+    fn inconsistent_settings_are_rejected() {
+        let mut config = wasmtime::Config::default();
+        for invalid in [
+            EngineSettings {
+                max_wasm_stack: Some(0),
+                ..settings()
+            },
+            EngineSettings {
+                max_wasm_stack: Some(16 << 20),
+                ..settings()
+            },
+            EngineSettings {
+                wasm_proposals: Some(vec![WasmProposal::Threads]),
+                ..settings()
+            },
+            EngineSettings {
+                wasm_proposals: Some(vec![WasmProposal::RelaxedSimd]),
+                ..settings()
+            },
+        ] {
+            assert!(invalid.apply(&mut config).is_err());
+        }
+    }
+}
@@ -1,15 +1,67 @@
-use std::sync::{Condvar, Mutex};
+use std::sync::Mutex;
 
-use rustler::Env;
+use rustler::{Env, LocalPid, Term};
+use tokio::sync::oneshot;
 
 pub struct CallbackTokenResource {
     pub token: CallbackToken,
 }
 
+/// The result of a callback as supplied by the Elixir host, (success, payload)
+pub type CallbackResult = (bool, Vec<u8>);
+
 pub struct CallbackToken {
-    pub continue_signal: Condvar,
-    /// Holds the return data from the call, (success, payload) in an Option in a mutex
-    pub return_value: Mutex<Option<(bool, Vec<u8>)>>,
+    /// Delivers the return data from the call to the host call awaiting it. Taken on first use, so a
+    /// callback can only ever complete once
+    sender: Mutex<Option<oneshot::Sender<CallbackResult>>>,
+}
+
+impl CallbackToken {
+    /// Creates a token along with the receiver a host call can await (without blocking a tokio worker)
+    /// until the Elixir host supplies the result
+    pub fn new() -> (CallbackToken, oneshot::Receiver<CallbackResult>) {
+        let (sender, receiver) = oneshot::channel();
+        (
+            CallbackToken {
+                sender: Mutex::new(Some(sender)),
+            },
+            receiver,
+        )
+    }
+
+    /// Supplies the result of the callback. Has no effect if the callback already has a result or the
+    /// host call stopped waiting for it
+    pub fn complete(&self, success: bool, payload: Vec<u8>) {
+        if let Some(sender) = self.sender.lock().unwrap().take() {
+            let _ = sender.send((success, payload));
+        }
+    }
+
+    /// Fails the callback with the given reason, waking anything waiting on it. Has no effect if the
+    /// callback already has a result
+    pub fn release(&self, reason: &str) {
+        self.complete(false, reason.as_bytes().to_vec());
+    }
+}
+
+/// Sends a message to a local process from a thread that is not managed by the BEAM, returning
+/// `false` if the process is not alive. Unlike `OwnedEnv::send_and_clear`, this lets callers find
+/// out that nobody is listening instead of waiting for a reply that will never come
+pub fn send_to_pid<F>(pid: &LocalPid, closure: F) -> bool
+where
+    F: for<'a> FnOnce(Env<'a>) -> Term<'a>,
+{
+    // SAFETY: the message env is allocated and freed here and never escapes this function. Terms
+    // built in it are only used to construct the message, which `enif_send` copies out of the env
+    unsafe {
+        let msg_env = rustler_sys::enif_alloc_env();
+        let lifetime_marker = ();
+        let env = Env::new(&lifetime_marker, msg_env);
+        let message = closure(env).as_c_arg();
+        let sent = rustler_sys::enif_send(std::ptr::null_mut(), pid.as_c_arg(), msg_env, message);
+        rustler_sys::enif_free_env(msg_env);
+        sent != 0
+    }
 }
 
 pub fn on_load(env: Env) -> bool {
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use data_encoding::HEXLOWER;
+use ring::digest::{digest, SHA256};
+use wasmparser::{
+    ComponentExternalKind, ComponentTypeRef, ExternalKind, Parser, Payload, ProducersSectionReader,
+    TypeRef,
+};
+
+use crate::component::is_component;
+
+/// Export through which the host calls into an actor using the wasmbus ABI
+const WASMBUS_GUEST_CALL: &str = "__guest_call";
+
+/// How the host talks to an actor
+#[derive(NifUnitEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Abi {
+    /// A core module exporting the wasmbus entrypoint
+    Wasmbus,
+    /// A component using WIT-defined interfaces
+    Component,
+    /// A core module the runtime can't call
+    Unknown,
+}
+
+#[derive(NifMap, Debug)]
+pub struct ModuleImport {
+    pub module: String,
+    pub name: String,
+    pub kind: String,
+}
+
+#[derive(NifMap, Debug)]
+pub struct ModuleExport {
+    pub name: String,
+    pub kind: String,
+}
+
+#[derive(NifMap, Debug)]
+pub struct ModuleMemory {
+    pub initial_pages: u64,
+    pub maximum_pages: Option<u64>,
+    pub memory64: bool,
+    pub shared: bool,
+    /// Whether the memory is provided by the host rather than defined by the module
+    pub imported: bool,
+}
+
+#[derive(NifMap, Debug)]
+pub struct CustomSection {
+    pub name: String,
+    pub size_bytes: u64,
+}
+
+/// What can be learned about a module or component without compiling it
+#[derive(NifMap, Debug)]
+pub struct ModuleInfo {
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub abi: Abi,
+    /// Imports and exports of the module, or for a component those of the component itself rather
+    /// than of the core modules inside it
+    pub imports: Vec<ModuleImport>,
+    pub exports: Vec<ModuleExport>,
+    /// Memories of every core module, including those nested in a component
+    pub memories: Vec<ModuleMemory>,
+    pub custom_sections: Vec<CustomSection>,
+    /// The contents of the `producers` section, e.g. `"language" => ["Rust"]`, with versions appended
+    /// to the names that have one
+    pub producers: HashMap<String, Vec<String>>,
+}
+
+/// Parses a module or component. Only the structure is read, function bodies are neither validated
+/// nor compiled
+pub(crate) fn inspect(bytes: &[u8]) -> anyhow::Result<ModuleInfo> {
+    let mut info = ModuleInfo {
+        sha256: HEXLOWER.encode(digest(&SHA256, bytes).as_ref()),
+        size_bytes: bytes.len() as u64,
+        abi: Abi::Unknown,
+        imports: Vec::new(),
+        exports: Vec::new(),
+        memories: Vec::new(),
+        custom_sections: Vec::new(),
+        producers: HashMap::new(),
+    };
+    // how many nested modules or components the parser is inside of
+    let mut depth = 0usize;
+    for payload in Parser::new(0).parse_all(bytes) {
+        let payload = payload.context("invalid WebAssembly binary")?;
+        let top_level = depth == 0;
+        match payload {
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => depth += 1,
+            Payload::End(_) => depth = depth.saturating_sub(1),
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    if let TypeRef::Memory(memory) = import.ty {
+                        info.memories.push(ModuleMemory {
+                            initial_pages: memory.initial,
+                            maximum_pages: memory.maximum,
+                            memory64: memory.memory64,
+                            shared: memory.shared,
+                            imported: true,
+                        });
+                    }
+                    if top_level {
+                        info.imports.push(ModuleImport {
+                            module: import.module.to_string(),
+                            name: import.name.to_string(),
+                            kind: type_ref_kind(&import.ty).to_string(),
+                        });
+                    }
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for memory in reader {
+                    let memory = memory?;
+                    info.memories.push(ModuleMemory {
+                        initial_pages: memory.initial,
+                        maximum_pages: memory.maximum,
+                        memory64: memory.memory64,
+                        shared: memory.shared,
+                        imported: false,
+                    });
+                }
+            }
+            Payload::ExportSection(reader) if top_level => {
+                for export in reader {
+                    let export = export?;
+                    info.exports.push(ModuleExport {
+                        name: export.name.to_string(),
+                        kind: external_kind(export.kind).to_string(),
+                    });
+                }
+            }
+            Payload::ComponentImportSection(reader) if top_level => {
+                for import in reader {
+                    let import = import?;
+                    info.imports.push(ModuleImport {
+                        module: String::new(),
+                        name: import.name.to_string(),
+                        kind: component_type_ref_kind(&import.ty).to_string(),
+                    });
+                }
+            }
+            Payload::ComponentExportSection(reader) if top_level => {
+                for export in reader {
+                    let export = export?;
+                    info.exports.push(ModuleExport {
+                        name: export.name.to_string(),
+                        kind: component_external_kind(export.kind).to_string(),
+                    });
+                }
+            }
+            Payload::CustomSection(reader) if top_level => {
+                if reader.name() == "producers" {
+                    let producers =
+                        ProducersSectionReader::new(reader.data(), reader.data_offset())?;
+                    for field in producers {
+                        let field = field?;
+                        let values = info.producers.entry(field.name.to_string()).or_default();
+                        for value in field.values {
+                            let value = value?;
+                            values.push(if value.version.is_empty() {
+                                value.name.to_string()
+                            } else {
+                                format!("{} {}", value.name, value.version)
+                            });
+                        }
+                    }
+                }
+                info.custom_sections.push(CustomSection {
+                    name: reader.name().to_string(),
+                    size_bytes: reader.data().len() as u64,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    info.abi = if is_component(bytes) {
+        Abi::Component
+    } else if info
+        .exports
+        .iter()
+        .any(|e| e.name == WASMBUS_GUEST_CALL && e.kind == "function")
+    {
+        Abi::Wasmbus
+    } else {
+        Abi::Unknown
+    };
+    Ok(info)
+}
+
+fn type_ref_kind(ty: &TypeRef) -> &'static str {
+    match ty {
+        TypeRef::Func(_) => "function",
+        TypeRef::Table(_) => "table",
+        TypeRef::Memory(_) => "memory",
+        TypeRef::Global(_) => "global",
+        TypeRef::Tag(_) => "tag",
+    }
+}
+
+fn external_kind(kind: ExternalKind) -> &'static str {
+    match kind {
+        ExternalKind::Func => "function",
+        ExternalKind::Table => "table",
+        ExternalKind::Memory => "memory",
+        ExternalKind::Global => "global",
+        ExternalKind::Tag => "tag",
+    }
+}
+
+fn component_type_ref_kind(ty: &ComponentTypeRef) -> &'static str {
+    match ty {
+        ComponentTypeRef::Module(_) => "module",
+        ComponentTypeRef::Func(_) => "function",
+        ComponentTypeRef::Value(_) => "value",
+        ComponentTypeRef::Type(..) => "type",
+        ComponentTypeRef::Instance(_) => "instance",
+        ComponentTypeRef::Component(_) => "component",
+    }
+}
+
+fn component_external_kind(kind: ComponentExternalKind) -> &'static str {
+    match kind {
+        ComponentExternalKind::Module => "module",
+        ComponentExternalKind::Func => "function",
+        ComponentExternalKind::Value => "value",
+        ComponentExternalKind::Type => "type",
+        ComponentExternalKind::Instance => "instance",
+        ComponentExternalKind::Component => "component",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{inspect, Abi};
+
+    fn name(s: &str) -> Vec<u8> {
+        let mut encoded = vec![s.len() as u8];
+        encoded.extend_from_slice(s.as_bytes());
+        encoded
+    }
+
+    fn section(id: u8, contents: Vec<u8>) -> Vec<u8> {
+        let mut encoded = vec![id, contents.len() as u8];
+        encoded.extend(contents);
+        encoded
+    }
+
+    /// A module importing a memory, defining another, exporting the wasmbus entrypoint and carrying a
+    /// producers section
+    fn module() -> Vec<u8> {
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        let mut imports = vec![1];
+        imports.extend(name("env"));
+        imports.extend(name("memory"));
+        imports.extend([0x02, 0x00, 0x01]);
+        bytes.extend(section(2, imports));
+        bytes.extend(section(5, vec![1, 0x01, 0x01, 0x02]));
+        let mut exports = vec![1];
+        exports.extend(name("__guest_call"));
+        exports.extend([0x00, 0x00]);
+        bytes.extend(section(7, exports));
+        let mut producers = name("producers");
+        producers.push(1);
+        producers.extend(name("language"));
+        producers.push(1);
+        producers.extend(name("Rust"));
+        producers.extend(name("1.70"));
+        bytes.extend(section(0, producers));
+        bytes
+    }
+
+    #[test]
+    fn reports_the_structure_of_a_module() {
+        let info = inspect(&module()).unwrap();
+        assert_eq!(info.abi, Abi::Wasmbus);
+        assert_eq!(info.imports.len(), 1);
+        assert_eq!(
+            (
+                info.imports[0].module.as_str(),
+                info.imports[0].kind.as_str()
+            ),
+            ("env", "memory")
+        );
+        assert_eq!(info.exports[0].name, "__guest_call");
+        assert_eq!(info.memories.len(), 2);
+        assert!(info.memories[0].imported);
+        assert_eq!(info.memories[1].maximum_pages, Some(2));
+        assert_eq!(info.custom_sections[0].name, "producers");
+        assert_eq!(info.producers["language"], vec!["Rust 1.70"]);
+        assert_eq!(info.sha256.len(), 64);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(inspect(b"not wasm").is_err());
+    }
+}
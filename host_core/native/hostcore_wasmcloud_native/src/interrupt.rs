@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use anyhow::bail;
+use wasmcloud::actor::Instance;
+use wasmtime::{Engine, StoreContextMut, WasmBacktrace};
+
+use crate::profile::Profiler;
+
+/// How often the engine's epoch advances while guests that need the ticks are running. Guests check
+/// in with the runtime on these ticks, even in the middle of a loop that never calls the host
+pub(crate) const EPOCH_INTERVAL: Duration = Duration::from_millis(1);
+/// Ticks between the checks of a call that isn't being profiled, which only look for cancellation
+const CANCELLATION_CHECK_TICKS: u64 = 10;
+
+/// Advances an engine's epoch every `EPOCH_INTERVAL`, but only while at least one guest holds an
+/// `EpochTicks`. It ticks from a thread of its own rather than the tokio pool, which guests that
+/// never yield can fill. An idle runtime leaves that thread parked. The ticker stops when this is
+/// dropped
+pub(crate) struct EpochTicker {
+    shared: Arc<TickerShared>,
+}
+
+struct TickerShared {
+    state: Mutex<TickerState>,
+    /// Wakes the parked ticker when a guest starts, or when the ticker is dropped
+    wake: Condvar,
+}
+
+#[derive(Default)]
+struct TickerState {
+    /// Guests currently relying on the ticks
+    active: usize,
+    stopped: bool,
+}
+
+/// Keeps the epoch advancing for as long as it is held
+pub(crate) struct EpochTicks {
+    shared: Arc<TickerShared>,
+}
+
+impl EpochTicker {
+    pub(crate) fn new() -> EpochTicker {
+        EpochTicker {
+            shared: Arc::new(TickerShared {
+                state: Mutex::new(TickerState::default()),
+                wake: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Starts the thread advancing `engine`'s epoch whenever a guest asks for ticks
+    pub(crate) fn start(&self, engine: Engine) -> std::io::Result<()> {
+        let shared = self.shared.clone();
+        std::thread::Builder::new()
+            .name("epoch-ticker".to_string())
+            .spawn(move || loop {
+                {
+                    let state = shared.state.lock().unwrap();
+                    let state = shared
+                        .wake
+                        .wait_while(state, |state| state.active == 0 && !state.stopped)
+                        .unwrap();
+                    if state.stopped {
+                        return;
+                    }
+                }
+                std::thread::sleep(EPOCH_INTERVAL);
+                engine.increment_epoch();
+            })?;
+        Ok(())
+    }
+
+    /// Keeps the epoch advancing until the returned guard is dropped
+    pub(crate) fn ticks(&self) -> EpochTicks {
+        let mut state = self.shared.state.lock().unwrap();
+        state.active += 1;
+        if state.active == 1 {
+            self.shared.wake.notify_one();
+        }
+        EpochTicks {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stopped = true;
+        self.shared.wake.notify_one();
+    }
+}
+
+impl Drop for EpochTicks {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().active -= 1;
+    }
+}
+
+/// Sets an instance up for the engine's epoch ticks. The instance traps at the first check after
+/// `cancelled` is set, and a profiled instance samples its stack on every tick
+pub(crate) fn configure(
+    instance: &mut Instance,
+    cancelled: Arc<AtomicBool>,
+    profiler: Option<Arc<Profiler>>,
+) {
+    let ticks = if profiler.is_some() {
+        1
+    } else {
+        CANCELLATION_CHECK_TICKS
+    };
+    instance.set_epoch_deadline(ticks);
+    instance.epoch_deadline_callback(deadline_callback(cancelled, profiler, ticks));
+}
+
+fn deadline_callback<T>(
+    cancelled: Arc<AtomicBool>,
+    profiler: Option<Arc<Profiler>>,
+    ticks: u64,
+) -> impl FnMut(StoreContextMut<T>) -> anyhow::Result<u64> + Send + Sync + 'static {
+    move |store| {
+        if cancelled.load(Ordering::SeqCst) {
+            bail!("actor call was cancelled");
+        }
+        if let Some(profiler) = &profiler {
+            profiler.sample(&WasmBacktrace::capture(&store));
+        }
+        Ok(ticks)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+
+    use wasmtime::{Config, Engine, Instance, Module, Store};
+
+    use super::{deadline_callback, EpochTicker};
+
+    #[test]
+    fn cancelled_guests_trap_even_if_they_fill_every_worker() {
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config).unwrap();
+        let module =
+            Module::new(&engine, r#"(module (func (export "spin") (loop br 0)))"#).unwrap();
+        let ticker = EpochTicker::new();
+        ticker.start(engine.clone()).unwrap();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        // none of the guests ever yields, so each holds on to its worker until it traps
+        let (sender, results) = mpsc::channel();
+        for _ in 0..*crate::TOKIO_WORKERS {
+            let ticks = ticker.ticks();
+            let (engine, module, cancelled, sender) = (
+                engine.clone(),
+                module.clone(),
+                cancelled.clone(),
+                sender.clone(),
+            );
+            crate::spawn(async move {
+                let _ticks = ticks;
+                let mut store = Store::new(&engine, ());
+                store.set_epoch_deadline(1);
+                store.epoch_deadline_callback(deadline_callback(cancelled, None, 1));
+                let instance = Instance::new(&mut store, &module, &[]).unwrap();
+                let spin = instance
+                    .get_typed_func::<(), ()>(&mut store, "spin")
+                    .unwrap();
+                sender.send(spin.call(&mut store, ())).unwrap();
+            });
+        }
+
+        // the guests keep running through the ticks before they are cancelled
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(results.try_recv().is_err());
+        cancelled.store(true, Ordering::SeqCst);
+
+        for _ in 0..*crate::TOKIO_WORKERS {
+            let err = results
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .unwrap_err();
+            assert!(format!("{err:?}").contains("actor call was cancelled"));
+        }
+    }
+}
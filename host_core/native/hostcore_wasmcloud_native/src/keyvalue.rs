@@ -0,0 +1,388 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use wascap::jwt;
+
+use crate::builtin::Builtin;
+use crate::inv::{deserialize, serialize};
+
+/// Contract ID of the capability served by the builtin key-value store
+pub(crate) const CONTRACT_KEYVALUE: &str = "wasmcloud:keyvalue";
+
+#[derive(Deserialize)]
+struct SetRequest {
+    key: String,
+    value: String,
+    /// Seconds until the value expires, 0 for never
+    #[serde(default)]
+    expires: u32,
+}
+
+#[derive(Deserialize)]
+struct IncrementRequest {
+    key: String,
+    value: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListItemRequest {
+    list_name: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListRangeRequest {
+    list_name: String,
+    start: i32,
+    stop: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetItemRequest {
+    set_name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct GetResponse {
+    value: String,
+    exists: bool,
+}
+
+enum Entry {
+    Value {
+        value: String,
+        expires_at: Option<Instant>,
+    },
+    List(Vec<String>),
+    Set(BTreeSet<String>),
+}
+
+/// An in-process store serving `wasmcloud:keyvalue` host calls, so that key-value actors can run
+/// without a provider during development and tests. Semantics follow the Redis provider: lists and
+/// sets live in the same keyspace as plain values, and using a key as the wrong type is an error.
+/// That keyspace is shared by every actor on the runtime rather than partitioned per actor. Nothing
+/// is persisted, the store lives and dies with its runtime
+#[derive(Default)]
+pub(crate) struct KeyValueStore {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+#[async_trait]
+impl Builtin for KeyValueStore {
+    async fn handle(
+        &self,
+        _claims: &jwt::Claims<jwt::Actor>,
+        operation: &str,
+        payload: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        self.perform(operation, payload)
+    }
+}
+
+impl KeyValueStore {
+    fn perform(&self, operation: &str, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap();
+        remove_expired(&mut entries);
+
+        match operation {
+            "KeyValue.Get" => {
+                let key: String = decode(payload)?;
+                let response = match entries.get(&key) {
+                    None => GetResponse {
+                        value: String::new(),
+                        exists: false,
+                    },
+                    Some(Entry::Value { value, .. }) => GetResponse {
+                        value: value.clone(),
+                        exists: true,
+                    },
+                    Some(_) => bail!(wrong_type(&key, "value")),
+                };
+                encode(response)
+            }
+            "KeyValue.Set" => {
+                let SetRequest {
+                    key,
+                    value,
+                    expires,
+                } = decode(payload)?;
+                let expires_at =
+                    (expires > 0).then(|| Instant::now() + Duration::from_secs(expires.into()));
+                entries.insert(key, Entry::Value { value, expires_at });
+                Ok(Vec::new())
+            }
+            "KeyValue.Del" => {
+                let key: String = decode(payload)?;
+                encode(entries.remove(&key).is_some())
+            }
+            "KeyValue.Contains" => {
+                let key: String = decode(payload)?;
+                encode(entries.contains_key(&key))
+            }
+            "KeyValue.Increment" => {
+                let IncrementRequest { key, value } = decode(payload)?;
+                let entry = entries.entry(key.clone()).or_insert_with(|| Entry::Value {
+                    value: "0".to_string(),
+                    expires_at: None,
+                });
+                let Entry::Value { value: current, .. } = entry else {
+                    bail!(wrong_type(&key, "value"));
+                };
+                let incremented = current
+                    .parse::<i32>()
+                    .map_err(|_| anyhow!("value of `{key}` is not an integer"))?
+                    .checked_add(value)
+                    .ok_or_else(|| anyhow!("incrementing `{key}` would overflow"))?;
+                *current = incremented.to_string();
+                encode(incremented)
+            }
+            "KeyValue.ListAdd" => {
+                let ListItemRequest { list_name, value } = decode(payload)?;
+                let entry = entries
+                    .entry(list_name.clone())
+                    .or_insert_with(|| Entry::List(Vec::new()));
+                let Entry::List(list) = entry else {
+                    bail!(wrong_type(&list_name, "list"));
+                };
+                list.push(value);
+                encode(list.len() as u32)
+            }
+            "KeyValue.ListDel" => {
+                let ListItemRequest { list_name, value } = decode(payload)?;
+                let removed = match entries.get_mut(&list_name) {
+                    None => false,
+                    Some(Entry::List(list)) => match list.iter().position(|v| *v == value) {
+                        Some(i) => {
+                            list.remove(i);
+                            true
+                        }
+                        None => false,
+                    },
+                    Some(_) => bail!(wrong_type(&list_name, "list")),
+                };
+                encode(removed)
+            }
+            "KeyValue.ListRange" => {
+                let ListRangeRequest {
+                    list_name,
+                    start,
+                    stop,
+                } = decode(payload)?;
+                let values = match entries.get(&list_name) {
+                    None => Vec::new(),
+                    Some(Entry::List(list)) => list_range(list, start, stop),
+                    Some(_) => bail!(wrong_type(&list_name, "list")),
+                };
+                encode(values)
+            }
+            "KeyValue.ListClear" => {
+                let list_name: String = decode(payload)?;
+                encode(remove_typed(&mut entries, &list_name, "list")?)
+            }
+            "KeyValue.SetAdd" => {
+                let SetItemRequest { set_name, value } = decode(payload)?;
+                let entry = entries
+                    .entry(set_name.clone())
+                    .or_insert_with(|| Entry::Set(BTreeSet::new()));
+                let Entry::Set(set) = entry else {
+                    bail!(wrong_type(&set_name, "set"));
+                };
+                encode(u32::from(set.insert(value)))
+            }
+            "KeyValue.SetDel" => {
+                let SetItemRequest { set_name, value } = decode(payload)?;
+                let removed = match entries.get_mut(&set_name) {
+                    None => false,
+                    Some(Entry::Set(set)) => set.remove(&value),
+                    Some(_) => bail!(wrong_type(&set_name, "set")),
+                };
+                encode(u32::from(removed))
+            }
+            "KeyValue.SetQuery" => {
+                let set_name: String = decode(payload)?;
+                let members = match entries.get(&set_name) {
+                    None => Vec::new(),
+                    Some(Entry::Set(set)) => set.iter().cloned().collect(),
+                    Some(_) => bail!(wrong_type(&set_name, "set")),
+                };
+                encode(members)
+            }
+            "KeyValue.SetIntersection" => {
+                let set_names: Vec<String> = decode(payload)?;
+                let mut sets = sets(&entries, &set_names)?.into_iter();
+                let first = sets.next().cloned().unwrap_or_default();
+                let members = sets.fold(first, |acc, set| &acc & set);
+                encode(members.into_iter().collect::<Vec<_>>())
+            }
+            "KeyValue.SetUnion" => {
+                let set_names: Vec<String> = decode(payload)?;
+                let members = sets(&entries, &set_names)?
+                    .into_iter()
+                    .fold(BTreeSet::new(), |acc, set| &acc | set);
+                encode(members.into_iter().collect::<Vec<_>>())
+            }
+            "KeyValue.SetClear" => {
+                let set_name: String = decode(payload)?;
+                encode(remove_typed(&mut entries, &set_name, "set")?)
+            }
+            _ => bail!("operation {operation} is not supported by the builtin keyvalue store"),
+        }
+    }
+}
+
+fn decode<'de, T: Deserialize<'de>>(payload: &'de [u8]) -> anyhow::Result<T> {
+    deserialize(payload).map_err(|e| anyhow!("invalid keyvalue request: {e}"))
+}
+
+fn encode<T: Serialize>(response: T) -> anyhow::Result<Vec<u8>> {
+    serialize(response).map_err(|e| anyhow!("failed to serialize keyvalue response: {e}"))
+}
+
+fn wrong_type(key: &str, expected: &str) -> String {
+    format!("WRONGTYPE: `{key}` does not hold a {expected}")
+}
+
+fn remove_expired(entries: &mut HashMap<String, Entry>) {
+    let now = Instant::now();
+    entries.retain(
+        |_, entry| !matches!(entry, Entry::Value { expires_at: Some(at), .. } if *at <= now),
+    );
+}
+
+/// Removes a list or set, returning whether it existed
+fn remove_typed(
+    entries: &mut HashMap<String, Entry>,
+    key: &str,
+    expected: &str,
+) -> anyhow::Result<bool> {
+    match entries.get(key) {
+        None => Ok(false),
+        Some(Entry::List(_)) if expected == "list" => Ok(entries.remove(key).is_some()),
+        Some(Entry::Set(_)) if expected == "set" => Ok(entries.remove(key).is_some()),
+        Some(_) => bail!(wrong_type(key, expected)),
+    }
+}
+
+/// Looks up the named sets, treating missing ones as empty
+fn sets<'a>(
+    entries: &'a HashMap<String, Entry>,
+    set_names: &[String],
+) -> anyhow::Result<Vec<&'a BTreeSet<String>>> {
+    static EMPTY: BTreeSet<String> = BTreeSet::new();
+    set_names
+        .iter()
+        .map(|name| match entries.get(name) {
+            None => Ok(&EMPTY),
+            Some(Entry::Set(set)) => Ok(set),
+            Some(_) => bail!(wrong_type(name, "set")),
+        })
+        .collect()
+}
+
+/// The elements between `start` and `stop` inclusive, where negative indices count back from the
+/// end of the list, as with Redis' LRANGE
+fn list_range(list: &[String], start: i32, stop: i32) -> Vec<String> {
+    let len = list.len() as i64;
+    let index = |i: i32| if i < 0 { len + i as i64 } else { i as i64 };
+    let start = index(start).max(0);
+    let stop = index(stop).min(len - 1);
+    if start > stop {
+        return Vec::new();
+    }
+    list[start as usize..=stop as usize].to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Serialize;
+
+    use super::KeyValueStore;
+    use crate::inv::{deserialize, serialize};
+
+    fn call<T: Serialize>(store: &KeyValueStore, operation: &str, request: T) -> Vec<u8> {
+        store
+            .perform(operation, &serialize(request).unwrap())
+            .unwrap()
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ListItem<'a> {
+        list_name: &'a str,
+        value: &'a str,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ListRange<'a> {
+        list_name: &'a str,
+        start: i32,
+        stop: i32,
+    }
+
+    #[derive(Serialize)]
+    struct Increment<'a> {
+        key: &'a str,
+        value: i32,
+    }
+
+    #[test]
+    fn increment_counts_from_zero() {
+        let store = KeyValueStore::default();
+        let inc = Increment {
+            key: "counter",
+            value: 2,
+        };
+        assert_eq!(
+            deserialize::<i32>(&call(&store, "KeyValue.Increment", &inc)).unwrap(),
+            2
+        );
+        assert_eq!(
+            deserialize::<i32>(&call(&store, "KeyValue.Increment", &inc)).unwrap(),
+            4
+        );
+        assert!(deserialize::<bool>(&call(&store, "KeyValue.Contains", "counter")).unwrap());
+    }
+
+    #[test]
+    fn lists_support_ranges_and_reject_value_ops() {
+        let store = KeyValueStore::default();
+        for value in ["a", "b", "c"] {
+            call(
+                &store,
+                "KeyValue.ListAdd",
+                ListItem {
+                    list_name: "letters",
+                    value,
+                },
+            );
+        }
+        let range = |start, stop| {
+            deserialize::<Vec<String>>(&call(
+                &store,
+                "KeyValue.ListRange",
+                ListRange {
+                    list_name: "letters",
+                    start,
+                    stop,
+                },
+            ))
+            .unwrap()
+        };
+        assert_eq!(range(0, -1), vec!["a", "b", "c"]);
+        assert_eq!(range(1, 5), vec!["b", "c"]);
+        assert!(range(2, 1).is_empty());
+
+        assert!(store
+            .perform("KeyValue.Get", &serialize("letters").unwrap())
+            .is_err());
+    }
+}
@@ -16,28 +16,49 @@ use bindle::{filters::BindleFilter, provider::Provider};
 use chrono::NaiveDateTime;
 use nkeys::KeyPair;
 use provider_archive::ProviderArchive;
-use rustler::{Atom, Binary, Error};
+use rustler::{Atom, Binary, Env, Error, OwnedBinary};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio_stream::StreamExt;
 use wascap::prelude::*;
 
+mod actorlog;
 mod atoms;
+mod builtin;
+mod callctx;
 mod client;
+mod component;
+mod engine;
 mod environment;
+mod inspect;
+mod interrupt;
 mod inv;
+mod keyvalue;
+mod metrics;
+mod modcache;
+mod numbergen;
 mod objstore;
 mod oci;
 mod par;
+mod policy;
+mod profile;
+mod record;
 mod task;
+mod wasi;
 mod wasmruntime;
 
 lazy_static! {
     static ref CHUNKING_STORE: RwLock<Option<ObjectStore>> = RwLock::new(None);
 }
 
+/// Number of worker threads of the tokio runtime that actor calls run on
+pub(crate) static TOKIO_WORKERS: Lazy<usize> =
+    Lazy::new(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+
 // Static tokio runtime required for the NIF to interact with async Rust APIs
 static TOKIO: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
     tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(*TOKIO_WORKERS)
+        .enable_time()
         .build()
         .expect("Failed to start tokio runtime")
 });
@@ -152,6 +173,9 @@ rustler::init!(
         dechunk_inv,
         chunk_inv,
         extract_claims,
+        inspect_module,
+        encode_call_context,
+        decode_call_context,
         generate_key,
         generate_invocation_bytes,
         validate_antiforgery,
@@ -165,8 +189,26 @@ rustler::init!(
         get_actor_bindle,
         wasmruntime::new,
         wasmruntime::version,
+        wasmruntime::engine_config,
+        wasmruntime::actor_log_stats,
+        wasmruntime::runtime_stats,
+        wasmruntime::module_cache_stats,
+        wasmruntime::purge_module_cache,
+        wasmruntime::reseed_numbergen,
         wasmruntime::start_actor,
+        wasmruntime::update_actor,
+        wasmruntime::register_local_actor,
+        wasmruntime::unregister_local_actor,
         wasmruntime::call_actor,
+        wasmruntime::cancel_actor_call,
+        wasmruntime::drain_actor,
+        wasmruntime::health_check_actor,
+        wasmruntime::start_recording,
+        wasmruntime::stop_recording,
+        wasmruntime::replay_recording,
+        wasmruntime::start_profiling,
+        wasmruntime::stop_profiling,
+        wasmruntime::drain_runtime,
         wasmruntime::receive_callback_result
     ],
     load = load
@@ -453,7 +495,7 @@ fn par_cache_path(
     par::cache_path(&subject, rev, &contract_id, &link_name)
 }
 
-/// Extracts the claims from the raw bytes of a _signed_ WebAssembly module/actor and returns them
+/// Extracts the claims from the raw bytes of a _signed_ WebAssembly module or component and returns them
 /// in the form of a simple struct that will bubble its way up to Elixir as a native struct
 #[rustler::nif]
 fn extract_claims(binary: Binary) -> Result<(Atom, Claims), Error> {
@@ -518,6 +560,37 @@ fn extract_claims(binary: Binary) -> Result<(Atom, Claims), Error> {
     Ok((atoms::ok(), out))
 }
 
+/// Reports the structure of a module or component without compiling it, so that the host can turn
+/// away modules it can't run before starting them
+#[rustler::nif(schedule = "DirtyCpu")]
+fn inspect_module(binary: Binary) -> Result<inspect::ModuleInfo, Error> {
+    inspect::inspect(binary.as_slice())
+        .map_err(|e| Error::Term(Box::new(format!("Failed to inspect module: {e:#}"))))
+}
+
+/// Encodes a call context to be passed to an actor call
+#[rustler::nif]
+fn encode_call_context<'a>(
+    env: Env<'a>,
+    context: callctx::CallContext,
+) -> Result<Binary<'a>, Error> {
+    let bytes = context
+        .encode()
+        .map_err(|e| Error::Term(Box::new(format!("Failed to encode call context: {e:#}"))))?;
+    let mut binary = OwnedBinary::new(bytes.len())
+        .ok_or_else(|| Error::Term(Box::new("Failed to allocate call context binary")))?;
+    binary.as_mut_slice().copy_from_slice(&bytes);
+    Ok(binary.release(env))
+}
+
+/// Decodes a call context produced by `encode_call_context`, failing for anything else such as an
+/// unversioned context or a bare span context
+#[rustler::nif]
+fn decode_call_context(binary: Binary) -> Result<callctx::CallContext, Error> {
+    callctx::CallContext::decode(binary.as_slice())
+        .map_err(|e| Error::Term(Box::new(format!("Failed to decode call context: {e:#}"))))
+}
+
 #[rustler::nif]
 fn generate_key(key_type: KeyType) -> Result<(String, String), Error> {
     let kp = match key_type {
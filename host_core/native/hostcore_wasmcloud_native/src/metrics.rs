@@ -0,0 +1,217 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use wasmtime::ResourceLimiter;
+
+/// What an actor call cost, returned to the host alongside the call's result
+#[derive(NifMap, Debug, Default, PartialEq, Eq)]
+pub struct CallMetrics {
+    pub wall_time_us: u64,
+    /// CPU time spent running the call, which is the guest's own apart from the host calls handled
+    /// natively. Waiting on host calls answered by the Elixir host costs none
+    pub guest_cpu_time_us: u64,
+    /// Fuel the guest burnt, `nil` unless the runtime was started with fuel metering
+    pub fuel_consumed: Option<u64>,
+    /// Largest size the actor's linear memory reached during the call, starting from the size it
+    /// was instantiated with
+    pub peak_memory_bytes: u64,
+    pub host_calls: u64,
+    /// Size of the call's payload
+    pub bytes_in: u64,
+    /// Size of the call's response
+    pub bytes_out: u64,
+    /// Total size of the payloads the actor sent with its host calls
+    pub host_call_bytes_out: u64,
+    /// Total size of the responses to the actor's host calls
+    pub host_call_bytes_in: u64,
+}
+
+/// Accumulates what an actor call costs while it runs
+#[derive(Default)]
+pub(crate) struct CallCounters {
+    host_calls: AtomicU64,
+    host_call_bytes_out: AtomicU64,
+    host_call_bytes_in: AtomicU64,
+    cpu_time_us: AtomicU64,
+    fuel_metered: AtomicBool,
+    fuel_consumed: AtomicU64,
+    peak_memory_bytes: Arc<AtomicU64>,
+}
+
+impl CallCounters {
+    pub(crate) fn record_host_call(&self, bytes_out: usize, bytes_in: usize) {
+        self.host_calls.fetch_add(1, Ordering::Relaxed);
+        self.host_call_bytes_out
+            .fetch_add(bytes_out as u64, Ordering::Relaxed);
+        self.host_call_bytes_in
+            .fetch_add(bytes_in as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_fuel(&self, fuel: u64) {
+        self.fuel_metered.store(true, Ordering::Relaxed);
+        self.fuel_consumed.fetch_add(fuel, Ordering::Relaxed);
+    }
+
+    /// Records the size of the guest's memory when it is instantiated and once the call is done,
+    /// which covers the calls whose memory never grows
+    pub(crate) fn record_memory_size(&self, bytes: usize) {
+        self.peak_memory_bytes
+            .fetch_max(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// A limiter that lets the guest's memory grow as it likes, recording how large it got
+    pub(crate) fn memory_tracker(&self) -> MemoryTracker {
+        MemoryTracker {
+            peak: self.peak_memory_bytes.clone(),
+        }
+    }
+
+    pub(crate) fn finish(
+        &self,
+        wall_time: Duration,
+        bytes_in: usize,
+        bytes_out: usize,
+    ) -> CallMetrics {
+        CallMetrics {
+            wall_time_us: wall_time.as_micros() as u64,
+            guest_cpu_time_us: self.cpu_time_us.load(Ordering::Relaxed),
+            fuel_consumed: self
+                .fuel_metered
+                .load(Ordering::Relaxed)
+                .then(|| self.fuel_consumed.load(Ordering::Relaxed)),
+            peak_memory_bytes: self.peak_memory_bytes.load(Ordering::Relaxed),
+            host_calls: self.host_calls.load(Ordering::Relaxed),
+            bytes_in: bytes_in as u64,
+            bytes_out: bytes_out as u64,
+            host_call_bytes_out: self.host_call_bytes_out.load(Ordering::Relaxed),
+            host_call_bytes_in: self.host_call_bytes_in.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Records the peak size of a guest's linear memories without limiting them
+pub(crate) struct MemoryTracker {
+    peak: Arc<AtomicU64>,
+}
+
+impl ResourceLimiter for MemoryTracker {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        self.peak
+            .fetch_max(current.max(desired) as u64, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        _desired: u32,
+        _maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
+
+/// Adds the CPU time the current thread spends in every poll of `future` to the call's counters.
+/// Tokio may move the future between threads, but each poll runs on a single one
+pub(crate) fn cpu_timed<F: Future>(counters: Arc<CallCounters>, future: F) -> CpuTimed<F> {
+    CpuTimed {
+        counters,
+        future: Box::pin(future),
+    }
+}
+
+pub(crate) struct CpuTimed<F> {
+    counters: Arc<CallCounters>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CpuTimed<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let started = thread_cpu_time();
+        let poll = self.future.as_mut().poll(cx);
+        let spent = thread_cpu_time().saturating_sub(started);
+        self.counters
+            .cpu_time_us
+            .fetch_add(spent.as_micros() as u64, Ordering::Relaxed);
+        poll
+    }
+}
+
+#[cfg(unix)]
+fn thread_cpu_time() -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `time` is a valid timespec for the duration of the call
+    if unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) } != 0 {
+        return Duration::ZERO;
+    }
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+/// Thread CPU time isn't measured on this platform, so calls report none
+#[cfg(not(unix))]
+fn thread_cpu_time() -> Duration {
+    Duration::ZERO
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use wasmtime::ResourceLimiter;
+
+    use super::{cpu_timed, CallCounters};
+
+    const WASM_PAGE: usize = 64 * 1024;
+
+    #[test]
+    fn cpu_time_only_counts_polls() {
+        let counters = Arc::new(CallCounters::default());
+        let future = cpu_timed(counters.clone(), async {
+            // waiting costs no CPU time
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let started = Instant::now();
+            while started.elapsed() < Duration::from_millis(20) {
+                std::hint::black_box(0);
+            }
+        });
+        crate::task::TOKIO.block_on(future);
+
+        let metrics = counters.finish(Duration::from_millis(70), 0, 0);
+        assert!(metrics.guest_cpu_time_us >= 15_000);
+        assert!(metrics.guest_cpu_time_us < 50_000);
+        assert_eq!(metrics.fuel_consumed, None);
+        assert_eq!(metrics.peak_memory_bytes, 0);
+    }
+
+    #[test]
+    fn peak_memory_starts_from_the_instantiated_size() {
+        let counters = CallCounters::default();
+        counters.record_memory_size(2 * WASM_PAGE);
+        let metrics = counters.finish(Duration::ZERO, 0, 0);
+        assert_eq!(metrics.peak_memory_bytes, 2 * WASM_PAGE as u64);
+
+        let mut tracker = counters.memory_tracker();
+        assert!(tracker
+            .memory_growing(2 * WASM_PAGE, 5 * WASM_PAGE, None)
+            .unwrap());
+        // a smaller size recorded later doesn't lower the peak
+        counters.record_memory_size(3 * WASM_PAGE);
+        let metrics = counters.finish(Duration::ZERO, 0, 0);
+        assert_eq!(metrics.peak_memory_bytes, 5 * WASM_PAGE as u64);
+    }
+}
@@ -0,0 +1,302 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+use data_encoding::HEXLOWER;
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+
+/// Where the engine keeps its compiled artifacts, relative to the cache directory
+const ARTIFACTS_DIR: &str = "artifacts";
+/// One entry per module started with the cache, named after the module's SHA-256
+const INDEX_DIR: &str = "modules";
+const ENGINE_CONFIG_FILE: &str = "engine-cache.toml";
+
+/// What the cache knows about a module, stored in its index entry
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    public_key: String,
+    size_bytes: u64,
+    /// Every runtime version and engine configuration the module was compiled for, each of which left
+    /// its own artifact behind
+    #[serde(default)]
+    builds: Vec<ModuleBuild>,
+    last_used: u64,
+}
+
+/// A runtime version and engine configuration a module was compiled for
+#[derive(NifMap, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ModuleBuild {
+    pub runtime_version: String,
+    /// Identifies the engine settings that change compiled code, see
+    /// `EffectiveEngineConfig::compilation_fingerprint`
+    pub engine_fingerprint: String,
+}
+
+/// A module that has been started with the cache
+#[derive(NifMap, Debug)]
+pub struct CachedModule {
+    pub sha256: String,
+    pub public_key: String,
+    pub size_bytes: u64,
+    pub builds: Vec<ModuleBuild>,
+    /// Seconds since the epoch at which the module was last started
+    pub last_used: u64,
+    /// Whether the module was compiled by this runtime's version and engine configuration. Artifacts
+    /// left behind by other versions or configurations are never loaded and get recompiled instead
+    pub compatible: bool,
+}
+
+#[derive(NifMap, Debug)]
+pub struct ModuleCacheStats {
+    pub directory: String,
+    pub modules: Vec<CachedModule>,
+    /// Total size of the compiled artifacts on disk
+    pub artifact_bytes: u64,
+    /// Modules started since the runtime was created that had been compiled by this runtime's version
+    /// and engine configuration before
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// An on-disk cache of compiled modules that survives host restarts. Runtimes pointed at the same
+/// directory share it.
+///
+/// Compiled artifacts are stored and looked up by the engine itself, keyed by the module's hash along
+/// with the engine version and its compilation settings, so an artifact produced by a different version
+/// or configuration is never loaded. An artifact that fails to deserialize is discarded and the module
+/// is compiled from its bytes again. Alongside the artifacts the cache keeps an index of the modules
+/// started with it, keyed by their SHA-256, which is what `stats` reports on. Each entry lists the
+/// runtime versions and engine configurations the module was compiled for, mirroring the engine's own
+/// cache key, so that a module is only counted as a hit when the engine has an artifact it can load
+pub(crate) struct ModuleCache {
+    dir: PathBuf,
+    build: ModuleBuild,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ModuleCache {
+    /// Creates the cache directory if needed and writes the engine's cache configuration into it
+    pub(crate) fn open(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(dir.join(ARTIFACTS_DIR))
+            .and_then(|_| fs::create_dir_all(dir.join(INDEX_DIR)))
+            .with_context(|| format!("failed to create module cache in {}", dir.display()))?;
+        let artifacts_dir = dir.join(ARTIFACTS_DIR);
+        let artifacts_dir = artifacts_dir.to_str().ok_or_else(|| {
+            anyhow!(
+                "module cache directory {} is not valid UTF-8",
+                dir.display()
+            )
+        })?;
+        let config = format!(
+            "[cache]\nenabled = true\ndirectory = {}\n",
+            toml_string(artifacts_dir)
+        );
+        fs::write(dir.join(ENGINE_CONFIG_FILE), config)
+            .context("failed to write the engine's cache configuration")?;
+        Ok(ModuleCache {
+            dir,
+            build: ModuleBuild {
+                runtime_version: String::new(),
+                engine_fingerprint: String::new(),
+            },
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    /// Sets the version of the runtime using the cache, which is only known once the runtime has been
+    /// built on top of the engine configured by `engine_config_file`, and the fingerprint of that
+    /// engine's configuration
+    pub(crate) fn for_runtime(
+        self,
+        runtime_version: impl Into<String>,
+        engine_fingerprint: impl Into<String>,
+    ) -> Self {
+        ModuleCache {
+            build: ModuleBuild {
+                runtime_version: runtime_version.into(),
+                engine_fingerprint: engine_fingerprint.into(),
+            },
+            ..self
+        }
+    }
+
+    /// The configuration file handed to the engine so that it caches its compiled artifacts here
+    pub(crate) fn engine_config_file(&self) -> PathBuf {
+        self.dir.join(ENGINE_CONFIG_FILE)
+    }
+
+    /// Records that a module was started, counting whether its artifact was already cached. Failing to
+    /// update the index only affects what `stats` reports, so errors are logged rather than returned
+    pub(crate) fn record(&self, bytes: &[u8], public_key: &str) {
+        let sha256 = HEXLOWER.encode(digest(&SHA256, bytes).as_ref());
+        let path = self.dir.join(INDEX_DIR).join(&sha256);
+        let mut builds = read_entry(&path).map_or_else(Vec::new, |entry| entry.builds);
+        if builds.contains(&self.build) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            builds.push(self.build.clone());
+        }
+
+        let entry = IndexEntry {
+            public_key: public_key.to_string(),
+            size_bytes: bytes.len() as u64,
+            builds,
+            last_used: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        };
+        let written = serde_json::to_vec(&entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| fs::write(&path, json).map_err(anyhow::Error::from));
+        if let Err(e) = written {
+            log::warn!("failed to update module cache index for {sha256}: {e}");
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ModuleCacheStats {
+        let modules = fs::read_dir(self.dir.join(INDEX_DIR))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|file| {
+                let entry = read_entry(&file.path())?;
+                Some(CachedModule {
+                    sha256: file.file_name().to_string_lossy().into_owned(),
+                    compatible: entry.builds.contains(&self.build),
+                    public_key: entry.public_key,
+                    size_bytes: entry.size_bytes,
+                    builds: entry.builds,
+                    last_used: entry.last_used,
+                })
+            })
+            .collect();
+        ModuleCacheStats {
+            directory: self.dir.to_string_lossy().into_owned(),
+            modules,
+            artifact_bytes: dir_size(&self.dir.join(ARTIFACTS_DIR)),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Removes every compiled artifact and index entry, returning how many modules were indexed.
+    /// Actors already started are unaffected, the next start of each module compiles it again
+    pub(crate) fn purge(&self) -> anyhow::Result<usize> {
+        let purged = fs::read_dir(self.dir.join(INDEX_DIR)).map_or(0, |entries| entries.count());
+        for sub in [ARTIFACTS_DIR, INDEX_DIR] {
+            let path = self.dir.join(sub);
+            if path.exists() {
+                fs::remove_dir_all(&path)
+                    .with_context(|| format!("failed to purge {}", path.display()))?;
+            }
+            fs::create_dir_all(&path)?;
+        }
+        Ok(purged)
+    }
+}
+
+fn read_entry(path: &Path) -> Option<IndexEntry> {
+    fs::read(path)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+}
+
+fn dir_size(path: &Path) -> u64 {
+    fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Quotes `s` as a TOML basic string
+fn toml_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{toml_string, ModuleCache, ARTIFACTS_DIR};
+
+    #[test]
+    fn quotes_paths_as_toml_strings() {
+        assert_eq!(
+            toml_string(r"C:\wasmcloud\cache"),
+            r#""C:\\wasmcloud\\cache""#
+        );
+        assert_eq!(toml_string("say \"hi\"\n"), r#""say \"hi\"\n""#);
+        assert_eq!(toml_string("bell\u{7}"), r#""bell\u0007""#);
+    }
+
+    #[test]
+    fn records_modules_and_purges_them() {
+        let dir = std::env::temp_dir().join(format!("modcache-{}", uuid::Uuid::new_v4()));
+        let cache = ModuleCache::open(&dir)
+            .unwrap()
+            .for_runtime("0.1.0", "fuel");
+        fs::write(dir.join(ARTIFACTS_DIR).join("artifact"), [0u8; 16]).unwrap();
+
+        cache.record(b"module", "MABC");
+        cache.record(b"module", "MABC");
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.modules.len(), 1);
+        assert!(stats.modules[0].compatible);
+        assert_eq!(stats.modules[0].public_key, "MABC");
+        assert_eq!(stats.artifact_bytes, 16);
+
+        // another engine configuration has artifacts of its own, which don't make this one's hits
+        let reconfigured = ModuleCache::open(&dir)
+            .unwrap()
+            .for_runtime("0.1.0", "epoch");
+        assert!(!reconfigured.stats().modules[0].compatible);
+        reconfigured.record(b"module", "MABC");
+        assert_eq!(reconfigured.stats().misses, 1);
+        assert_eq!(reconfigured.stats().modules[0].builds.len(), 2);
+        // while the first configuration's artifacts are still there
+        cache.record(b"module", "MABC");
+        assert_eq!(cache.stats().hits, 2);
+
+        let upgraded = ModuleCache::open(&dir)
+            .unwrap()
+            .for_runtime("0.2.0", "fuel");
+        assert!(!upgraded.stats().modules[0].compatible);
+        upgraded.record(b"module", "MABC");
+        assert_eq!(upgraded.stats().misses, 1);
+
+        assert_eq!(upgraded.purge().unwrap(), 1);
+        let stats = upgraded.stats();
+        assert!(stats.modules.is_empty());
+        assert_eq!(stats.artifact_bytes, 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
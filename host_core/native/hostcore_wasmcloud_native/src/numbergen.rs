@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, RngCore, SeedableRng};
+use ring::digest::{Context, SHA256};
+
+/// Source of randomness for the builtin numbergen capability. Unseeded, values come from the thread
+/// RNG. Seeded, every actor gets its own generator derived from the seed and its public key, so the
+/// values an actor receives can be reproduced no matter what other actors are doing
+pub(crate) struct Numbergen {
+    seed: Mutex<Option<u64>>,
+    actors: Mutex<HashMap<String, StdRng>>,
+}
+
+impl Numbergen {
+    pub(crate) fn new(seed: Option<u64>) -> Numbergen {
+        Numbergen {
+            seed: Mutex::new(seed),
+            actors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replaces the seed (or removes it with `None`), restarting every actor's sequence
+    pub(crate) fn reseed(&self, seed: Option<u64>) {
+        *self.seed.lock().unwrap() = seed;
+        self.actors.lock().unwrap().clear();
+    }
+
+    pub(crate) fn guid(&self, actor: &str) -> String {
+        let mut buf = uuid::Bytes::default();
+        self.with_rng(actor, |rng| rng.fill_bytes(&mut buf));
+        uuid::Builder::from_random_bytes(buf)
+            .into_uuid()
+            .to_string()
+    }
+
+    pub(crate) fn random32(&self, actor: &str) -> u32 {
+        self.with_rng(actor, |rng| rng.gen())
+    }
+
+    /// A value in `[min; max]`. An empty range is rejected before any generator is touched, so that a
+    /// bad argument from one actor can't take the seeded generators of every other actor down with it
+    pub(crate) fn random_in_range(&self, actor: &str, min: u32, max: u32) -> anyhow::Result<u32> {
+        if min > max {
+            bail!("invalid random range [{min};{max}], min must not be greater than max");
+        }
+        Ok(self.with_rng(actor, |rng| rng.gen_range(min..=max)))
+    }
+
+    fn with_rng<T>(&self, actor: &str, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        let seed = *self.seed.lock().unwrap();
+        match seed {
+            None => f(&mut thread_rng()),
+            Some(seed) => {
+                let mut actors = self.actors.lock().unwrap();
+                let rng = actors
+                    .entry(actor.to_string())
+                    .or_insert_with(|| StdRng::from_seed(actor_seed(seed, actor)));
+                f(rng)
+            }
+        }
+    }
+}
+
+/// Derives the seed of an actor's generator from the runtime seed and the actor's public key
+fn actor_seed(seed: u64, actor: &str) -> [u8; 32] {
+    let mut context = Context::new(&SHA256);
+    context.update(&seed.to_le_bytes());
+    context.update(actor.as_bytes());
+    let mut out = [0u8; 32];
+    out.copy_from_slice(context.finish().as_ref());
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::Numbergen;
+
+    const ACTOR: &str = "MCLOGOFFRJ4WK5XC6Z6WM7A3SAVHPHKDAVG6OMZVOOC6RQCN5IOKPTQL";
+    const OTHER_ACTOR: &str = "MBW3UGAIONCX3RIDDUGDCQIRGBQQOWS643CVICQ5EZ7SWNQPZLZTSQKU";
+
+    fn sequence(numbergen: &Numbergen, actor: &str) -> (String, u32, u32) {
+        (
+            numbergen.guid(actor),
+            numbergen.random32(actor),
+            numbergen.random_in_range(actor, 0, 100).unwrap(),
+        )
+    }
+
+    #[test]
+    fn seeded_numbergen_is_reproducible() {
+        let first = Numbergen::new(Some(42));
+        let second = Numbergen::new(Some(42));
+
+        // Interleaving another actor's calls doesn't disturb this actor's sequence
+        let expected = sequence(&first, ACTOR);
+        sequence(&second, OTHER_ACTOR);
+        assert_eq!(sequence(&second, ACTOR), expected);
+
+        assert_ne!(sequence(&Numbergen::new(Some(42)), OTHER_ACTOR), expected);
+        assert_ne!(sequence(&Numbergen::new(Some(7)), ACTOR), expected);
+
+        // Reseeding restarts the sequence from the beginning
+        first.reseed(Some(42));
+        assert_eq!(sequence(&first, ACTOR), expected);
+    }
+
+    #[test]
+    fn empty_ranges_are_rejected_without_poisoning_the_generators() {
+        let numbergen = Numbergen::new(Some(42));
+        assert!(numbergen.random_in_range(ACTOR, 10, 1).is_err());
+
+        // the rejected call neither broke nor advanced the actor's generator
+        let expected = sequence(&Numbergen::new(Some(42)), ACTOR);
+        assert_eq!(sequence(&numbergen, ACTOR), expected);
+    }
+}
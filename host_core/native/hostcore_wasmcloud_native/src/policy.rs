@@ -0,0 +1,148 @@
+use crate::inspect::ModuleImport;
+
+/// Matches imports by namespace and, optionally, function. Both may use `*` as a wildcard, e.g.
+/// `wasi_snapshot_preview1` / `sock_*`. The namespace of a core module import is its module name and its
+/// function is the imported name; a component import is matched on its name alone, e.g.
+/// `wasi:sockets/*`, by rules without a function
+#[derive(NifStruct, Clone, Debug)]
+#[module = "HostCore.WasmCloud.Runtime.ImportRule"]
+pub struct ImportRule {
+    namespace: String,
+    function: Option<String>,
+}
+
+impl ImportRule {
+    fn matches(&self, import: &ModuleImport) -> bool {
+        let (namespace, function) = if import.module.is_empty() {
+            (import.name.as_str(), None)
+        } else {
+            (import.module.as_str(), Some(import.name.as_str()))
+        };
+        glob(&self.namespace, namespace)
+            && match (&self.function, function) {
+                (None, _) => true,
+                (Some(pattern), Some(function)) => glob(pattern, function),
+                (Some(_), None) => false,
+            }
+    }
+}
+
+/// Which imports the modules started on a runtime may have. Denied imports take precedence over
+/// allowed ones
+#[derive(Default)]
+pub(crate) struct ImportPolicy {
+    /// When set, only imports matching one of these rules are allowed
+    allowed: Option<Vec<ImportRule>>,
+    denied: Vec<ImportRule>,
+}
+
+impl ImportPolicy {
+    pub(crate) fn new(allowed: Option<Vec<ImportRule>>, denied: Vec<ImportRule>) -> ImportPolicy {
+        ImportPolicy { allowed, denied }
+    }
+
+    /// Whether the policy allows every import, in which case modules needn't be inspected at all
+    pub(crate) fn is_open(&self) -> bool {
+        self.allowed.is_none() && self.denied.is_empty()
+    }
+
+    fn allows(&self, import: &ModuleImport) -> bool {
+        !self.denied.iter().any(|rule| rule.matches(import))
+            && self.allowed.as_ref().map_or(true, |allowed| {
+                allowed.iter().any(|rule| rule.matches(import))
+            })
+    }
+
+    /// Checks a module's imports, listing every one the policy doesn't allow
+    pub(crate) fn check(&self, imports: &[ModuleImport]) -> Result<(), String> {
+        let rejected: Vec<_> = imports
+            .iter()
+            .filter(|import| !self.allows(import))
+            .map(|import| {
+                if import.module.is_empty() {
+                    import.name.clone()
+                } else {
+                    format!("{}::{}", import.module, import.name)
+                }
+            })
+            .collect();
+        if rejected.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "module imports not allowed by the runtime's import policy: {}",
+                rejected.join(", ")
+            ))
+        }
+    }
+}
+
+/// Matches `text` against a pattern in which `*` stands for any run of characters
+fn glob(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=text.len())
+                .filter(|&i| text.is_char_boundary(i))
+                .any(|i| glob(rest, &text[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{glob, ImportPolicy, ImportRule};
+    use crate::inspect::ModuleImport;
+
+    fn import(module: &str, name: &str) -> ModuleImport {
+        ModuleImport {
+            module: module.to_string(),
+            name: name.to_string(),
+            kind: "function".to_string(),
+        }
+    }
+
+    fn rule(namespace: &str, function: Option<&str>) -> ImportRule {
+        ImportRule {
+            namespace: namespace.to_string(),
+            function: function.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn globs_match_any_run_of_characters() {
+        assert!(glob("sock_*", "sock_accept"));
+        assert!(glob("*", ""));
+        assert!(glob("wasi:*/tcp*", "wasi:sockets/tcp@0.2.0"));
+        assert!(!glob("sock_*", "path_open"));
+        assert!(!glob("fd_read", "fd_readdir"));
+    }
+
+    #[test]
+    fn denied_imports_are_listed() {
+        let policy = ImportPolicy::new(
+            Some(vec![
+                rule("wasmbus", None),
+                rule("wasi_snapshot_preview1", None),
+            ]),
+            vec![rule("wasi_snapshot_preview1", Some("sock_*"))],
+        );
+        let imports = [
+            import("wasmbus", "__host_call"),
+            import("wasi_snapshot_preview1", "fd_write"),
+            import("wasi_snapshot_preview1", "sock_accept"),
+            import("env", "abort"),
+            import("", "wasi:sockets/tcp"),
+        ];
+        assert_eq!(
+            policy.check(&imports).unwrap_err(),
+            "module imports not allowed by the runtime's import policy: \
+             wasi_snapshot_preview1::sock_accept, env::abort, wasi:sockets/tcp"
+        );
+        assert!(policy.check(&imports[..2]).is_ok());
+        assert!(ImportPolicy::default().check(&imports).is_ok());
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde_json::{json, Value};
+use wasmtime::WasmBacktrace;
+
+use crate::interrupt::EPOCH_INTERVAL;
+
+/// Version of the Gecko profile format written, which the Firefox profiler upgrades on import
+const GECKO_PROFILE_VERSION: u32 = 24;
+
+/// A function on a sampled guest stack
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Frame {
+    module: Option<String>,
+    func_index: u32,
+    func_name: Option<String>,
+}
+
+impl Frame {
+    fn label(&self) -> String {
+        let func = self
+            .func_name
+            .clone()
+            .unwrap_or_else(|| format!("wasm-function[{}]", self.func_index));
+        match &self.module {
+            Some(module) => format!("{module}!{func}"),
+            None => func,
+        }
+    }
+}
+
+struct Sample {
+    /// Milliseconds since profiling started
+    time_ms: f64,
+    /// Outermost frame first
+    frames: Vec<Frame>,
+}
+
+/// How a profile turned out
+#[derive(NifMap, Debug)]
+pub struct ProfileSummary {
+    pub path: String,
+    pub calls: usize,
+    pub samples: usize,
+}
+
+/// Collects guest stack samples from the first calls made on an actor once profiling starts
+pub(crate) struct Profiler {
+    path: PathBuf,
+    actor: String,
+    started: Instant,
+    started_at: SystemTime,
+    /// Calls still to be profiled
+    remaining_calls: AtomicUsize,
+    calls: AtomicUsize,
+    samples: Mutex<Vec<Sample>>,
+}
+
+impl Profiler {
+    /// Starts a profile of the next `calls` calls, to be written to `path`. The file is created right
+    /// away so that an unusable path is reported now rather than once the calls have been made
+    pub(crate) fn create(path: &Path, actor: &str, calls: usize) -> anyhow::Result<Profiler> {
+        File::create(path)
+            .with_context(|| format!("failed to create profile {}", path.display()))?;
+        Ok(Profiler {
+            path: path.to_path_buf(),
+            actor: actor.to_string(),
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            remaining_calls: AtomicUsize::new(calls),
+            calls: AtomicUsize::new(0),
+            samples: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Claims one of the calls to profile, `false` once all of them have been made
+    pub(crate) fn claim_call(&self) -> bool {
+        let claimed = self
+            .remaining_calls
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if claimed {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+        }
+        claimed
+    }
+
+    pub(crate) fn sample(&self, backtrace: &WasmBacktrace) {
+        let frames = backtrace
+            .frames()
+            .iter()
+            .rev()
+            .map(|frame| Frame {
+                module: frame.module_name().map(str::to_string),
+                func_index: frame.func_index(),
+                func_name: frame.func_name().map(str::to_string),
+            })
+            .collect();
+        let time_ms = self.started.elapsed().as_secs_f64() * 1000.0;
+        self.samples
+            .lock()
+            .unwrap()
+            .push(Sample { time_ms, frames });
+    }
+
+    /// Writes the samples taken so far to the profile file. Samples taken afterwards by calls still in
+    /// flight are not included
+    pub(crate) fn finish(&self) -> anyhow::Result<ProfileSummary> {
+        let samples = self.samples.lock().unwrap();
+        let file = File::create(&self.path)
+            .with_context(|| format!("failed to write profile {}", self.path.display()))?;
+        serde_json::to_writer(file, &self.gecko_profile(&samples))?;
+        Ok(ProfileSummary {
+            path: self.path.display().to_string(),
+            calls: self.calls.load(Ordering::SeqCst),
+            samples: samples.len(),
+        })
+    }
+
+    /// The samples as a single-threaded profile in the Gecko format, which the Firefox profiler opens
+    fn gecko_profile(&self, samples: &[Sample]) -> Value {
+        let mut strings = Interner::default();
+        let mut frames = Interner::default();
+        let mut stacks = Interner::default();
+        let mut sample_data = Vec::with_capacity(samples.len());
+        for sample in samples {
+            let mut stack = None;
+            for frame in &sample.frames {
+                let frame = frames.intern((strings.intern(frame.label()), frame.clone()));
+                stack = Some(stacks.intern((stack, frame)));
+            }
+            sample_data.push(json!([stack, sample.time_ms, 0]));
+        }
+
+        let start_time = self
+            .started_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64()
+            * 1000.0;
+        json!({
+            "meta": {
+                "version": GECKO_PROFILE_VERSION,
+                "startTime": start_time,
+                "shutdownTime": null,
+                "interval": EPOCH_INTERVAL.as_secs_f64() * 1000.0,
+                "stackwalk": 1,
+                "processType": 0,
+                "presymbolicated": true,
+                "product": format!("wasmCloud actor {}", self.actor),
+                "categories": [
+                    { "name": "WebAssembly", "color": "blue", "subcategories": ["Other"] }
+                ],
+                "markerSchema": [],
+            },
+            "libs": [],
+            "pausedRanges": [],
+            "processes": [],
+            "threads": [{
+                "name": self.actor,
+                "processType": "default",
+                "tid": 0,
+                "pid": 0,
+                "registerTime": 0,
+                "unregisterTime": null,
+                "samples": {
+                    "schema": { "stack": 0, "time": 1, "eventDelay": 2 },
+                    "data": sample_data,
+                },
+                "markers": {
+                    "schema": {
+                        "name": 0, "startTime": 1, "endTime": 2, "phase": 3, "category": 4,
+                        "data": 5
+                    },
+                    "data": [],
+                },
+                "stackTable": {
+                    "schema": { "prefix": 0, "frame": 1 },
+                    "data": stacks
+                        .values
+                        .iter()
+                        .map(|(prefix, frame)| json!([prefix, frame]))
+                        .collect::<Vec<_>>(),
+                },
+                "frameTable": {
+                    "schema": {
+                        "location": 0, "relevantForJS": 1, "innerWindowID": 2, "implementation": 3,
+                        "line": 4, "column": 5, "category": 6, "subcategory": 7
+                    },
+                    "data": frames
+                        .values
+                        .iter()
+                        .map(|(location, _)| json!([location, false, 0, null, null, null, 0, 0]))
+                        .collect::<Vec<_>>(),
+                },
+                "stringTable": strings.values,
+            }],
+        })
+    }
+}
+
+/// Assigns each distinct value the index of its first occurrence, as the tables of a profile do
+struct Interner<T> {
+    values: Vec<T>,
+    indices: HashMap<T, usize>,
+}
+
+impl<T> Default for Interner<T> {
+    fn default() -> Self {
+        Interner {
+            values: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + std::hash::Hash> Interner<T> {
+    fn intern(&mut self, value: T) -> usize {
+        if let Some(&index) = self.indices.get(&value) {
+            return index;
+        }
+        let index = self.values.len();
+        self.indices.insert(value.clone(), index);
+        self.values.push(value);
+        index
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Frame, Profiler, Sample};
+
+    fn frame(name: &str) -> Frame {
+        Frame {
+            module: Some("kvcounter".to_string()),
+            func_index: 0,
+            func_name: Some(name.to_string()),
+        }
+    }
+
+    #[test]
+    fn profiles_the_claimed_calls_only() {
+        let path = std::env::temp_dir().join(format!("profile-{}.json", uuid::Uuid::new_v4()));
+        let profiler = Profiler::create(&path, "MACTOR", 2).unwrap();
+        assert!(profiler.claim_call());
+        assert!(profiler.claim_call());
+        assert!(!profiler.claim_call());
+
+        profiler.samples.lock().unwrap().extend([
+            Sample {
+                time_ms: 1.0,
+                frames: vec![frame("__guest_call"), frame("handle_request")],
+            },
+            Sample {
+                time_ms: 2.0,
+                frames: vec![frame("__guest_call"), frame("increment")],
+            },
+        ]);
+        let summary = profiler.finish().unwrap();
+        assert_eq!((summary.calls, summary.samples), (2, 2));
+
+        let profile: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let thread = &profile["threads"][0];
+        // the shared outermost frame is a single stack both samples grow from
+        assert_eq!(thread["stackTable"]["data"].as_array().unwrap().len(), 3);
+        assert_eq!(thread["frameTable"]["data"].as_array().unwrap().len(), 3);
+        assert_eq!(thread["stringTable"][0], "kvcounter!__guest_call");
+        assert_eq!(thread["samples"]["data"][1][0], 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
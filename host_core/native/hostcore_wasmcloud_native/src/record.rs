@@ -0,0 +1,412 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use data_encoding::HEXLOWER;
+use ring::digest::{digest, SHA256};
+use rmp_serde::Deserializer;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use wascap::jwt;
+use wasmcloud::{
+    capability, Actor, Handle, HostInvocation, LoggingInvocation, NumbergenInvocation,
+    Runtime as WcRuntime,
+};
+
+use crate::inv;
+
+/// Written in the header of every recording, so that recordings made with a different layout are
+/// turned away rather than misread
+const RECORDING_VERSION: u32 = 1;
+
+tokio::task_local! {
+    /// The host calls made so far by the actor call being executed by the current task, `None` if the
+    /// actor isn't being recorded
+    pub(crate) static RECORDING: Option<Arc<Mutex<Vec<RecordedHostCall>>>>;
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordingHeader {
+    version: u32,
+    actor: String,
+    module_sha256: String,
+}
+
+/// One call an actor handled, with everything the host gave it along the way
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct RecordedCall {
+    pub operation: String,
+    #[serde(with = "serde_bytes")]
+    pub payload: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub call_context: Vec<u8>,
+    pub host_calls: Vec<RecordedHostCall>,
+    pub response: RecordedResult,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct RecordedHostCall {
+    binding: String,
+    request: HostRequest,
+    result: RecordedResult,
+}
+
+/// A host call as the actor made it
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub(crate) enum HostRequest {
+    Log {
+        level: String,
+        text: String,
+    },
+    GenerateGuid,
+    RandomInRange {
+        min: u32,
+        max: u32,
+    },
+    Random32,
+    Host {
+        namespace: String,
+        operation: String,
+        #[serde(with = "serde_bytes")]
+        payload: Vec<u8>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub(crate) enum RecordedResult {
+    Returned(Option<ByteBuf>),
+    Failed(String),
+}
+
+impl HostRequest {
+    pub(crate) fn of(invocation: &capability::Invocation) -> HostRequest {
+        match invocation {
+            capability::Invocation::Logging(LoggingInvocation::WriteLog { level, text }) => {
+                HostRequest::Log {
+                    level: format!("{level:?}"),
+                    text: text.clone(),
+                }
+            }
+            capability::Invocation::Numbergen(NumbergenInvocation::GenerateGuid) => {
+                HostRequest::GenerateGuid
+            }
+            capability::Invocation::Numbergen(NumbergenInvocation::RandomInRange { min, max }) => {
+                HostRequest::RandomInRange {
+                    min: *min,
+                    max: *max,
+                }
+            }
+            capability::Invocation::Numbergen(NumbergenInvocation::Random32) => {
+                HostRequest::Random32
+            }
+            capability::Invocation::Host(HostInvocation {
+                namespace,
+                operation,
+                payload,
+            }) => HostRequest::Host {
+                namespace: namespace.clone(),
+                operation: operation.clone(),
+                payload: payload.clone().unwrap_or_default(),
+            },
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            HostRequest::Log { level, .. } => format!("log at {level}"),
+            HostRequest::GenerateGuid => "generate GUID".to_string(),
+            HostRequest::RandomInRange { min, max } => format!("random in [{min};{max}]"),
+            HostRequest::Random32 => "random u32".to_string(),
+            HostRequest::Host {
+                namespace,
+                operation,
+                ..
+            } => format!("{namespace} {operation}"),
+        }
+    }
+}
+
+impl RecordedResult {
+    /// The outcome of a call on an actor, guest errors and traps alike
+    pub(crate) fn of_call(response: &anyhow::Result<Result<Option<Vec<u8>>, String>>) -> Self {
+        match response {
+            Ok(Ok(data)) => RecordedResult::Returned(data.clone().map(ByteBuf::from)),
+            Ok(Err(e)) => RecordedResult::Failed(e.clone()),
+            Err(e) => RecordedResult::Failed(format!("{e:#}")),
+        }
+    }
+
+    fn of_host_call(result: &anyhow::Result<Option<Vec<u8>>>) -> Self {
+        match result {
+            Ok(data) => RecordedResult::Returned(data.clone().map(ByteBuf::from)),
+            Err(e) => RecordedResult::Failed(format!("{e:#}")),
+        }
+    }
+
+    fn into_host_call(self) -> anyhow::Result<Option<Vec<u8>>> {
+        match self {
+            RecordedResult::Returned(data) => Ok(data.map(ByteBuf::into_vec)),
+            RecordedResult::Failed(e) => Err(anyhow!(e)),
+        }
+    }
+}
+
+/// Adds a host call to the recording of the current actor call, if it is being recorded
+pub(crate) fn record_host_call(
+    binding: String,
+    request: HostRequest,
+    result: &anyhow::Result<Option<Vec<u8>>>,
+) {
+    let _ = RECORDING.try_with(|recording| {
+        if let Some(host_calls) = recording {
+            host_calls.lock().unwrap().push(RecordedHostCall {
+                binding,
+                request,
+                result: RecordedResult::of_host_call(result),
+            });
+        }
+    });
+}
+
+/// Whether the actor call executed by the current task is being recorded
+pub(crate) fn is_recording() -> bool {
+    RECORDING
+        .try_with(|recording| recording.is_some())
+        .unwrap_or_default()
+}
+
+pub(crate) fn module_sha256(bytes: &[u8]) -> String {
+    HEXLOWER.encode(digest(&SHA256, bytes).as_ref())
+}
+
+/// Appends the calls made on an actor to a recording file. Each call is written whole once it is over,
+/// so calls running concurrently don't interleave
+pub(crate) struct Recorder {
+    file: Mutex<File>,
+    calls: AtomicUsize,
+}
+
+impl Recorder {
+    pub(crate) fn create(
+        path: &Path,
+        actor: &str,
+        module_sha256: &str,
+    ) -> anyhow::Result<Recorder> {
+        let mut file = File::create(path)
+            .with_context(|| format!("failed to create recording {}", path.display()))?;
+        let header = RecordingHeader {
+            version: RECORDING_VERSION,
+            actor: actor.to_string(),
+            module_sha256: module_sha256.to_string(),
+        };
+        file.write_all(&inv::serialize(header).map_err(|e| anyhow!("{e}"))?)?;
+        Ok(Recorder {
+            file: Mutex::new(file),
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    pub(crate) fn write(&self, call: &RecordedCall) -> anyhow::Result<()> {
+        let bytes = inv::serialize(call).map_err(|e| anyhow!("{e}"))?;
+        self.file.lock().unwrap().write_all(&bytes)?;
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// How many calls have been recorded
+    pub(crate) fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+fn read_recording(path: &Path) -> anyhow::Result<(RecordingHeader, Vec<RecordedCall>)> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("failed to read recording {}", path.display()))?;
+    let mut de = Deserializer::new(Cursor::new(bytes.as_slice()));
+    let header = RecordingHeader::deserialize(&mut de).context("not an actor recording")?;
+    if header.version != RECORDING_VERSION {
+        bail!("unsupported recording version {}", header.version);
+    }
+    let mut calls = Vec::new();
+    while (de.get_ref().position() as usize) < bytes.len() {
+        calls.push(
+            RecordedCall::deserialize(&mut de)
+                .with_context(|| format!("recorded call {} is truncated", calls.len()))?,
+        );
+    }
+    Ok((header, calls))
+}
+
+/// What happened when an actor was re-run against a recording
+#[derive(NifMap, Debug, Default)]
+pub struct ReplayReport {
+    /// The public key of the recorded actor
+    pub actor: String,
+    pub calls: usize,
+    /// Host calls answered from the recording
+    pub host_calls: usize,
+    /// Whether the module replayed differs from the one recorded
+    pub module_changed: bool,
+    /// Every point at which the replayed actor didn't do what the recorded one did
+    pub divergences: Vec<String>,
+}
+
+#[derive(Default)]
+struct ReplayState {
+    call: usize,
+    expected: VecDeque<RecordedHostCall>,
+    host_calls: usize,
+    divergences: Vec<String>,
+}
+
+/// Answers an actor's host calls from a recording. The first host call that doesn't match the recording
+/// fails, as does every later one in the same call
+struct ReplayHandler {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+#[async_trait]
+impl Handle<capability::Invocation> for ReplayHandler {
+    async fn handle(
+        &self,
+        _claims: &jwt::Claims<jwt::Actor>,
+        binding: String,
+        invocation: capability::Invocation,
+        _call_context: &Option<Vec<u8>>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let request = HostRequest::of(&invocation);
+        let mut state = self.state.lock().unwrap();
+        let call = state.call;
+        let divergence = match state.expected.pop_front() {
+            Some(expected) if expected.binding == binding && expected.request == request => {
+                state.host_calls += 1;
+                return expected.result.into_host_call();
+            }
+            Some(expected) => format!(
+                "call {call}: expected host call `{}` on `{}`, the actor made `{}` on `{binding}`",
+                expected.request.describe(),
+                expected.binding,
+                request.describe()
+            ),
+            None => format!(
+                "call {call}: the actor made host call `{}` on `{binding}`, which isn't recorded",
+                request.describe()
+            ),
+        };
+        state.expected.clear();
+        state.divergences.push(divergence);
+        bail!("host call diverged from the recording")
+    }
+}
+
+/// Re-runs the calls of a recording on a module, answering its host calls from the recording. Nothing
+/// reaches the Elixir host or any provider, and the actor runs without a WASI configuration
+pub(crate) fn replay(bytes: &[u8], path: &Path) -> anyhow::Result<ReplayReport> {
+    let (header, calls) = read_recording(path)?;
+
+    let state = Arc::new(Mutex::new(ReplayState::default()));
+    let handler: Box<dyn Handle<capability::Invocation>> = Box::new(ReplayHandler {
+        state: state.clone(),
+    });
+    let rt = WcRuntime::new(handler).context("failed to construct replay runtime")?;
+    let actor = Actor::new(&rt, bytes).context("failed to load actor from bytes")?;
+
+    let mut report = ReplayReport {
+        module_changed: header.module_sha256 != module_sha256(bytes),
+        actor: header.actor,
+        ..Default::default()
+    };
+    for (i, call) in calls.into_iter().enumerate() {
+        {
+            let mut state = state.lock().unwrap();
+            state.call = i;
+            state.expected = call.host_calls.into();
+        }
+        let response = crate::task::TOKIO.block_on(actor.call_with_context(
+            call.operation.clone(),
+            Some(call.payload),
+            call.call_context,
+        ));
+        let response = RecordedResult::of_call(&response);
+
+        let mut state = state.lock().unwrap();
+        if let Some(skipped) = state.expected.front() {
+            let divergence = format!(
+                "call {i}: the actor didn't make {} recorded host call(s), starting with `{}`",
+                state.expected.len(),
+                skipped.request.describe()
+            );
+            state.divergences.push(divergence);
+        }
+        if response != call.response {
+            state.divergences.push(format!(
+                "call {i}: `{}` returned {response:?} rather than the recorded {:?}",
+                call.operation, call.response
+            ));
+        }
+        report.calls += 1;
+    }
+
+    let mut state = state.lock().unwrap();
+    report.host_calls = state.host_calls;
+    report.divergences = std::mem::take(&mut state.divergences);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_bytes::ByteBuf;
+
+    use super::{
+        read_recording, HostRequest, RecordedCall, RecordedHostCall, RecordedResult, Recorder,
+        RECORDING_VERSION,
+    };
+
+    #[test]
+    fn recordings_read_back_call_by_call() {
+        let path = std::env::temp_dir().join(format!("recording-{}", uuid::Uuid::new_v4()));
+        let recorder = Recorder::create(&path, "MACTOR", "abc").unwrap();
+        for operation in ["HttpServer.HandleRequest", "HealthResponder.HealthRequest"] {
+            recorder
+                .write(&RecordedCall {
+                    operation: operation.to_string(),
+                    payload: vec![1, 2, 3],
+                    call_context: Vec::new(),
+                    host_calls: vec![RecordedHostCall {
+                        binding: "default".to_string(),
+                        request: HostRequest::Random32,
+                        result: RecordedResult::Returned(Some(ByteBuf::from(vec![4]))),
+                    }],
+                    response: RecordedResult::Failed("boom".to_string()),
+                })
+                .unwrap();
+        }
+        assert_eq!(recorder.calls(), 2);
+
+        let (header, calls) = read_recording(&path).unwrap();
+        assert_eq!(
+            (header.version, header.actor.as_str()),
+            (RECORDING_VERSION, "MACTOR")
+        );
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[1].operation, "HealthResponder.HealthRequest");
+        assert_eq!(calls[0].host_calls[0].request, HostRequest::Random32);
+        assert_eq!(
+            calls[0].response,
+            RecordedResult::Failed("boom".to_string())
+        );
+
+        // a call cut short by a crash is reported rather than silently dropped
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() - 2);
+        std::fs::write(&path, bytes).unwrap();
+        assert!(read_recording(&path).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
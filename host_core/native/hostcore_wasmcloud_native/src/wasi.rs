@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context as TaskContext, Poll};
+
+use anyhow::{bail, Context};
+use tokio::io::AsyncWrite;
+use wasmcloud::actor::Instance;
+
+/// Most output kept from one stream of one call, anything past it is dropped
+const MAX_CAPTURED_OUTPUT: usize = 64 * 1024;
+
+/// The WASI environment an actor is started with
+#[derive(NifStruct, Default)]
+#[module = "HostCore.WasmCloud.Runtime.WasiConfig"]
+pub struct ExWasiConfig {
+    env: HashMap<String, String>,
+    args: Vec<String>,
+    /// Directories to preopen read-only, relative to the runtime's sandbox directory
+    preopens: Vec<String>,
+}
+
+/// A validated WASI environment, applied to every instance of the actor
+pub(crate) struct WasiSettings {
+    env: Vec<(String, String)>,
+    args: Vec<String>,
+    /// Host directories and the guest paths they are mounted at
+    preopens: Vec<(PathBuf, String)>,
+}
+
+impl ExWasiConfig {
+    /// Resolves the preopens against the runtime's sandbox directory, refusing any that are missing or
+    /// would escape it. An actor can only be given directories when the runtime has a sandbox
+    pub(crate) fn resolve(self, sandbox_dir: Option<&Path>) -> anyhow::Result<WasiSettings> {
+        let preopens = if self.preopens.is_empty() {
+            Vec::new()
+        } else {
+            let Some(sandbox_dir) = sandbox_dir else {
+                bail!("preopened directories require the runtime to have a WASI sandbox directory");
+            };
+            let sandbox = sandbox_dir.canonicalize().with_context(|| {
+                format!("WASI sandbox {} is not accessible", sandbox_dir.display())
+            })?;
+            self.preopens
+                .iter()
+                .map(|dir| resolve_preopen(&sandbox, dir))
+                .collect::<anyhow::Result<_>>()?
+        };
+        let mut env: Vec<_> = self.env.into_iter().collect();
+        env.sort();
+        Ok(WasiSettings {
+            env,
+            args: self.args,
+            preopens,
+        })
+    }
+}
+
+/// Finds a preopen inside the sandbox, mounting it at the same path from the guest's root
+fn resolve_preopen(sandbox: &Path, dir: &str) -> anyhow::Result<(PathBuf, String)> {
+    let relative = dir.trim_start_matches('/');
+    let host_path = sandbox
+        .join(relative)
+        .canonicalize()
+        .with_context(|| format!("preopened directory `{dir}` does not exist in the sandbox"))?;
+    if !host_path.starts_with(sandbox) {
+        bail!("preopened directory `{dir}` is outside of the sandbox");
+    }
+    if !host_path.is_dir() {
+        bail!("preopened path `{dir}` is not a directory");
+    }
+    Ok((host_path, format!("/{relative}")))
+}
+
+impl WasiSettings {
+    /// Sets up an instance's WASI context, sending its stdout and stderr to the given buffers
+    pub(crate) fn configure(
+        &self,
+        instance: &mut Instance,
+        stdout: CapturedOutput,
+        stderr: CapturedOutput,
+    ) -> anyhow::Result<()> {
+        instance
+            .envs(&self.env)
+            .args(&self.args)
+            .stdout(stdout)
+            .stderr(stderr);
+        for (host_path, guest_path) in &self.preopens {
+            instance
+                .preopened_dir(host_path, guest_path, true)
+                .with_context(|| format!("failed to preopen {}", host_path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects what a guest writes to one of its output streams during a call, so that it can be
+/// forwarded to the actor log once the call is over
+#[derive(Clone, Default)]
+pub(crate) struct CapturedOutput {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    truncated: Arc<Mutex<bool>>,
+}
+
+impl CapturedOutput {
+    /// The complete lines written so far, with a final note if some output had to be dropped
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let buffer = self.buffer.lock().unwrap();
+        let mut lines: Vec<_> = String::from_utf8_lossy(&buffer)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect();
+        if *self.truncated.lock().unwrap() {
+            lines.push(format!(
+                "[output truncated after {MAX_CAPTURED_OUTPUT} bytes]"
+            ));
+        }
+        lines
+    }
+}
+
+impl AsyncWrite for CapturedOutput {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buffer = self.buffer.lock().unwrap();
+        let room = MAX_CAPTURED_OUTPUT.saturating_sub(buffer.len());
+        if buf.len() > room {
+            *self.truncated.lock().unwrap() = true;
+        }
+        buffer.extend_from_slice(&buf[..buf.len().min(room)]);
+        // the guest is never told about dropped output
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tokio::io::AsyncWriteExt;
+
+    use super::{CapturedOutput, ExWasiConfig, MAX_CAPTURED_OUTPUT};
+
+    #[test]
+    fn preopens_stay_inside_the_sandbox() {
+        let sandbox = std::env::temp_dir().join(format!("wasi-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(sandbox.join("data")).unwrap();
+        let config = |preopens: &[&str]| ExWasiConfig {
+            preopens: preopens.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        };
+
+        let settings = config(&["data"]).resolve(Some(&sandbox)).unwrap();
+        assert_eq!(settings.preopens[0].1, "/data");
+        assert!(config(&["../"]).resolve(Some(&sandbox)).is_err());
+        assert!(config(&["missing"]).resolve(Some(&sandbox)).is_err());
+        assert!(config(&["data"]).resolve(None).is_err());
+        assert!(config(&[]).resolve(None).is_ok());
+
+        fs::remove_dir_all(sandbox).unwrap();
+    }
+
+    #[test]
+    fn captured_output_is_bounded() {
+        let mut output = CapturedOutput::default();
+        crate::task::TOKIO.block_on(async {
+            output.write_all(b"hello\n\nworld\n").await.unwrap();
+        });
+        assert_eq!(output.lines(), vec!["hello", "world"]);
+
+        crate::task::TOKIO.block_on(async {
+            output
+                .write_all(&vec![b'x'; MAX_CAPTURED_OUTPUT])
+                .await
+                .unwrap();
+        });
+        assert!(output.lines().last().unwrap().contains("truncated"));
+    }
+}
@@ -1,9 +1,28 @@
-use anyhow::{self, bail, Context};
+use anyhow::{self, anyhow, bail, Context};
 use async_trait::async_trait;
-use log::{error, trace};
-use rand::{thread_rng, Rng, RngCore};
+use log::{error, trace, warn};
+use serde::{Deserialize, Serialize};
 
-use crate::environment::CallbackToken;
+use crate::actorlog::{ActorLog, ActorLogMetadata, LogLevel, LogOverflow, LogQueue, LogQueueStats};
+use crate::builtin::BuiltinRegistry;
+use crate::callctx::{CallContext, CallerEntity};
+use crate::component::is_component;
+use crate::engine::{EffectiveEngineConfig, EngineSettings, OptLevel, WasmProposal};
+use crate::environment::{send_to_pid, CallbackResult, CallbackToken};
+use crate::inspect::{inspect, Abi};
+use crate::interrupt::{self, EpochTicker};
+use crate::inv::{self, WasmCloudEntity};
+use crate::keyvalue::{KeyValueStore, CONTRACT_KEYVALUE};
+use crate::metrics::{self, CallCounters, CallMetrics};
+use crate::modcache::{ModuleCache, ModuleCacheStats};
+use crate::numbergen::Numbergen;
+use crate::policy::{ImportPolicy, ImportRule};
+use crate::profile::{ProfileSummary, Profiler};
+use crate::record::{
+    is_recording, module_sha256, record_host_call, HostRequest, RecordedCall, RecordedResult,
+    Recorder, ReplayReport, RECORDING,
+};
+use crate::wasi::{CapturedOutput, ExWasiConfig, WasiSettings};
 use rustler::{
     env::{OwnedEnv, SavedTerm},
     resource::ResourceArc,
@@ -11,12 +30,21 @@ use rustler::{
     Binary, Encoder, Env, Error, LocalPid, NifResult, Term,
 };
 
-use std::sync::{Condvar, Mutex};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio::task::AbortHandle;
 use wascap::jwt;
+use wascap::prelude::KeyPair;
+use wascap::wasm::extract_claims;
 use wasmcloud::{
-    capability, logging, numbergen, Actor, Handle, HostInvocation, LoggingInvocation,
-    NumbergenInvocation, Runtime as WcRuntime,
+    capability, numbergen, Actor, Handle, HostInvocation, LoggingInvocation, NumbergenInvocation,
+    Runtime as WcRuntime,
 };
+use wasmtime::Config as EngineConfig;
 
 use crate::{atoms, environment::CallbackTokenResource};
 
@@ -24,18 +52,740 @@ use crate::{atoms, environment::CallbackTokenResource};
 /// Elixir to maintain a long-lived reference to it
 pub struct RuntimeResource {
     pub inner: WcRuntime,
+    state: Arc<RuntimeState>,
+    /// Compiled modules kept on disk across restarts, `None` if no cache directory was configured
+    module_cache: Option<ModuleCache>,
+    engine_config: EffectiveEngineConfig,
+    /// The only directory actors may be given access to through WASI preopens
+    wasi_sandbox_dir: Option<PathBuf>,
+    /// Which imports the modules started on this runtime may have
+    import_policy: ImportPolicy,
 }
 
 /// A wrapper around an instance of a precompiled wasmCloud actor. This will be used inside a `ResourceArc` to allow
 /// Elixir to maintain a long-lived reference to it
 pub struct ActorResource {
-    pub actor: Actor,
+    /// The module calls are made on. A live update replaces it, calls already running keep the one they
+    /// started with
+    version: RwLock<Arc<ActorVersion>>,
+    pub public_key: String,
+    call_alias: Option<String>,
+    runtime: Arc<RuntimeState>,
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+    /// Slots for concurrent calls, `None` if the actor takes any number of calls at once
+    concurrency: Option<Arc<Semaphore>>,
+    /// How many calls may wait for a slot before further calls are turned away as busy
+    max_queued: Option<usize>,
+    queued: AtomicUsize,
+    /// The actor's WASI environment, `None` to run it with the runtime's default one and discard its
+    /// output
+    wasi: Option<WasiSettings>,
+    /// Where the actor's calls are being recorded, if they are
+    recorder: Mutex<Option<Arc<Recorder>>>,
+    /// Collects stack samples of the actor's calls while it is being profiled
+    profiler: Mutex<Option<Arc<Profiler>>>,
+}
+
+/// Limits and environment applied to an actor when it is started
+#[derive(NifStruct)]
+#[module = "HostCore.WasmCloud.Runtime.ActorConfig"]
+pub struct ExActorConfig {
+    max_concurrency: Option<usize>,
+    max_queued: Option<usize>,
+    wasi: Option<ExWasiConfig>,
+}
+
+/// One module an actor has run, either the one it was started with or one it was live updated to
+struct ActorVersion {
+    actor: Actor,
+    identity: ActorIdentity,
+    sha256: String,
+}
+
+/// What a live update has to preserve about an actor
+struct ActorIdentity {
+    claims: Option<jwt::Claims<jwt::Actor>>,
+    abi: Abi,
+}
+
+impl ActorIdentity {
+    fn of(bytes: &[u8]) -> ActorIdentity {
+        ActorIdentity {
+            claims: extract_claims(bytes)
+                .ok()
+                .flatten()
+                .map(|token| token.claims),
+            abi: if is_component(bytes) {
+                Abi::Component
+            } else {
+                Abi::Wasmbus
+            },
+        }
+    }
+
+    fn public_key(&self) -> String {
+        self.claims
+            .as_ref()
+            .map(|c| c.subject.clone())
+            .unwrap_or_default()
+    }
+
+    fn call_alias(&self) -> Option<String> {
+        self.claims
+            .as_ref()
+            .and_then(|c| c.metadata.as_ref())
+            .and_then(|m| m.call_alias.clone())
+    }
+
+    /// Checks that a module with the `next` identity may replace one with this identity: both have to be
+    /// signed by the same issuer for the same subject, the replacement has to be of a higher revision,
+    /// called through the same ABI and keep the call alias other actors may reach the actor by
+    fn check_update(&self, next: &ActorIdentity) -> Result<(), String> {
+        let (Some(current_claims), Some(next_claims)) = (&self.claims, &next.claims) else {
+            return Err("live updates require both modules to carry valid claims".to_string());
+        };
+        if next_claims.subject != current_claims.subject {
+            return Err(format!(
+                "replacement module is actor {} rather than {}",
+                next_claims.subject, current_claims.subject
+            ));
+        }
+        if next_claims.issuer != current_claims.issuer {
+            return Err(format!(
+                "replacement module was issued by {} rather than {}",
+                next_claims.issuer, current_claims.issuer
+            ));
+        }
+        let revision = |claims: &jwt::Claims<jwt::Actor>| {
+            claims
+                .metadata
+                .as_ref()
+                .and_then(|m| m.rev)
+                .unwrap_or_default()
+        };
+        let (current_rev, next_rev) = (revision(current_claims), revision(next_claims));
+        if next_rev <= current_rev {
+            return Err(format!(
+                "replacement revision {next_rev} is not newer than the running revision {current_rev}"
+            ));
+        }
+        if next.abi != self.abi {
+            return Err(format!(
+                "replacement module uses the {:?} ABI rather than {:?}",
+                next.abi, self.abi
+            ));
+        }
+        if next.call_alias() != self.call_alias() {
+            return Err("live updates cannot change an actor's call alias".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl ActorResource {
+    /// The module a call starting now should run on
+    fn current(&self) -> Arc<ActorVersion> {
+        self.version.read().unwrap().clone()
+    }
+
+    /// Performs a call, adding it to the actor's recording if it is being recorded
+    async fn call(
+        &self,
+        operation: String,
+        payload: Vec<u8>,
+        call_context: Vec<u8>,
+    ) -> anyhow::Result<Result<Option<Vec<u8>>, String>> {
+        // an actor called by a recorded one has its host calls kept out of the caller's recording
+        let recorder = self.recorder.lock().unwrap().clone();
+        let Some(recorder) = recorder else {
+            return RECORDING
+                .scope(None, self.run(operation, payload, call_context))
+                .await;
+        };
+
+        let host_calls = Arc::new(Mutex::new(Vec::new()));
+        let response = RECORDING
+            .scope(
+                Some(host_calls.clone()),
+                self.run(operation.clone(), payload.clone(), call_context.clone()),
+            )
+            .await;
+        let call = RecordedCall {
+            operation,
+            payload,
+            call_context,
+            host_calls: std::mem::take(&mut *host_calls.lock().unwrap()),
+            response: RecordedResult::of_call(&response),
+        };
+        if let Err(e) = recorder.write(&call) {
+            warn!("failed to record call on actor {}: {e:#}", self.public_key);
+        }
+        response
+    }
+
+    /// Performs a call on a fresh instance of the actor. An actor with a WASI configuration gets it
+    /// applied to the instance, and whatever the guest wrote to stdout and stderr is forwarded to the
+    /// actor log once the call is over. On a runtime whose engine interrupts guests, every instance
+    /// has to be set up for the engine's epoch ticks: the guest traps once its call is cancelled, and
+    /// that of a profiled call samples its stack. The growth of the instance's memory and, on a runtime
+    /// metering fuel, the fuel it burns are added to the call's metrics
+    async fn run(
+        &self,
+        operation: String,
+        payload: Vec<u8>,
+        call_context: Vec<u8>,
+    ) -> anyhow::Result<Result<Option<Vec<u8>>, String>> {
+        let version = self.current();
+        let counters = current_call_counters();
+        let stdout = CapturedOutput::default();
+        let stderr = CapturedOutput::default();
+        let mut instance = version
+            .actor
+            .instantiate()
+            .await
+            .context("failed to instantiate actor")?;
+        if let Some(wasi) = &self.wasi {
+            wasi.configure(&mut instance, stdout.clone(), stderr.clone())?;
+        }
+        let _ticks = self.runtime.epoch_ticker.as_ref().map(|ticker| {
+            let profiler = self.profiler.lock().unwrap().clone();
+            interrupt::configure(
+                &mut instance,
+                current_call_cancellation(),
+                profiler.filter(|p| p.claim_call()),
+            );
+            ticker.ticks()
+        });
+        counters.record_memory_size(instance.memory_size());
+        instance.limiter(counters.memory_tracker());
+        if self.runtime.fuel_metering {
+            instance
+                .add_fuel(FUEL_PER_CALL)
+                .context("failed to fuel actor instance")?;
+        }
+        let response = instance
+            .call_with_context(operation, Some(payload), call_context)
+            .await;
+        if let Some(fuel) = instance.fuel_consumed() {
+            counters.record_fuel(fuel);
+        }
+        counters.record_memory_size(instance.memory_size());
+
+        for (level, output) in [(LogLevel::Info, stdout), (LogLevel::Warn, stderr)] {
+            for text in output.lines() {
+                self.write_log(level, text).await;
+            }
+        }
+        response
+    }
+
+    /// Writes to the actor log on behalf of the actor itself rather than one of its host calls
+    async fn write_log(&self, level: LogLevel, text: String) {
+        let (invocation_id, operation) = current_call_ids();
+        let metadata = ActorLogMetadata {
+            actor_id: self.public_key.clone(),
+            call_alias: self.call_alias.clone(),
+            invocation_id,
+            operation,
+        };
+        self.runtime
+            .log_queue
+            .push(ActorLog {
+                level,
+                text,
+                metadata,
+            })
+            .await;
+    }
+
+    /// Decides whether a call may run right away, has to wait for a slot, or should be turned away
+    /// because the actor's queue is full (`None`)
+    fn admit(actor: &ResourceArc<ActorResource>) -> Option<Admission> {
+        let Some(semaphore) = &actor.concurrency else {
+            return Some(Admission::Unlimited(InFlightCall::new(actor.clone())));
+        };
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return Some(Admission::Running(permit, InFlightCall::new(actor.clone())));
+        }
+        let queued = QueuedCall::new(actor.clone(), semaphore.clone());
+        if actor.max_queued.map_or(false, |max| queued.position > max) {
+            return None;
+        }
+        Some(Admission::Queued(queued))
+    }
+}
+
+/// A call's claim on one of its actor's concurrency slots. An admitted call is always counted as either
+/// queued or in flight, so that draining waits for it
+enum Admission {
+    /// The actor has no concurrency limit
+    Unlimited(InFlightCall),
+    Running(OwnedSemaphorePermit, InFlightCall),
+    /// Every slot is taken, the call holds a place in the actor's queue until one frees up
+    Queued(QueuedCall),
+}
+
+/// A call holding its slot, counted as in flight until dropped
+struct RunningCall {
+    _permit: Option<OwnedSemaphorePermit>,
+    _in_flight: InFlightCall,
+}
+
+impl Admission {
+    /// Waits for a slot if necessary, returning the call holding it for the rest of the call
+    async fn start(self) -> RunningCall {
+        match self {
+            Admission::Unlimited(in_flight) => RunningCall {
+                _permit: None,
+                _in_flight: in_flight,
+            },
+            Admission::Running(permit, in_flight) => RunningCall {
+                _permit: Some(permit),
+                _in_flight: in_flight,
+            },
+            Admission::Queued(queued) => {
+                let permit = queued.semaphore.clone().acquire_owned().await.ok();
+                // in flight before it leaves the queue, so it is never counted as neither
+                let in_flight = InFlightCall::new(queued.actor.clone());
+                drop(queued);
+                RunningCall {
+                    _permit: permit,
+                    _in_flight: in_flight,
+                }
+            }
+        }
+    }
+}
+
+/// Counts a call as queued on its actor until dropped, which also covers calls cancelled while queued
+struct QueuedCall {
+    actor: ResourceArc<ActorResource>,
+    semaphore: Arc<Semaphore>,
+    /// Number of calls queued, this one included, when it joined the queue
+    position: usize,
+}
+
+impl QueuedCall {
+    fn new(actor: ResourceArc<ActorResource>, semaphore: Arc<Semaphore>) -> QueuedCall {
+        let position = actor.queued.fetch_add(1, Ordering::SeqCst) + 1;
+        actor.runtime.queued.fetch_add(1, Ordering::SeqCst);
+        actor
+            .runtime
+            .update_actor_stats(&actor.public_key, |stats| stats.queued += 1);
+        QueuedCall {
+            actor,
+            semaphore,
+            position,
+        }
+    }
+}
+
+impl Drop for QueuedCall {
+    fn drop(&mut self) {
+        self.actor.queued.fetch_sub(1, Ordering::SeqCst);
+        self.actor.runtime.queued.fetch_sub(1, Ordering::SeqCst);
+        self.actor
+            .runtime
+            .update_actor_stats(&self.actor.public_key, |stats| {
+                stats.queued = stats.queued.saturating_sub(1)
+            });
+    }
+}
+
+impl Drop for ActorResource {
+    fn drop(&mut self) {
+        self.runtime.update_actor_stats(&self.public_key, |stats| {
+            stats.instances = stats.instances.saturating_sub(1)
+        });
+    }
+}
+
+/// Load of the instances of one actor started on a runtime
+#[derive(NifMap, Clone, Default)]
+pub struct ActorStats {
+    instances: u64,
+    /// Calls running on the actor's instances, those waiting on host calls included
+    in_flight: u64,
+    /// Calls waiting for one of the actor's concurrency slots
+    queued: u64,
+}
+
+/// A snapshot of how loaded a runtime is
+#[derive(NifMap)]
+pub struct RuntimeStats {
+    /// Actor instances started on the runtime and not yet freed
+    actors: u64,
+    /// Instance, in-flight and queued call counts keyed by actor public key
+    per_actor: HashMap<String, ActorStats>,
+    /// Calls running, those waiting on host calls included
+    in_flight: u64,
+    /// Calls waiting for one of their actor's concurrency slots
+    queued: u64,
+    /// Host calls waiting on the Elixir host
+    pending_callbacks: u64,
+    /// Worker threads executing actor calls, shared by all runtimes
+    workers: u64,
+    /// A rough upper bound on the fraction of the workers busy with this runtime's calls, not a
+    /// measurement: calls in flight less the host calls waiting on the Elixir host, relative to the
+    /// number of workers. Calls waiting on host calls handled natively, or on anything else, count as
+    /// busy, and the workers' time spent on other runtimes isn't seen
+    estimated_utilisation: f64,
+    /// Calls accepted since the runtime started
+    calls: u64,
+    /// Calls that completed with an error, whether raised by the runtime or returned by the guest
+    call_errors: u64,
+    /// Calls cancelled before they completed
+    calls_cancelled: u64,
+}
+
+/// Bookkeeping shared by a runtime, the actors started on it and its host call handler, used to
+/// drain and shut the runtime down
+struct RuntimeState {
+    log_queue: LogQueue,
+    numbergen: Numbergen,
+    /// Capabilities served in-process, consulted before host calls are sent to Elixir
+    builtins: BuiltinRegistry,
+    /// Running actor instances that other actors on this runtime can call directly
+    local_actors: Mutex<LocalActors>,
+    /// Set once the runtime stops accepting new actors and calls
+    draining: AtomicBool,
+    /// Set once pending callbacks have been released, after which host calls fail immediately
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+    /// Started instances, in-flight and queued calls of each actor, keyed by public key
+    actor_stats: Mutex<HashMap<String, ActorStats>>,
+    calls: AtomicU64,
+    call_errors: AtomicU64,
+    calls_cancelled: AtomicU64,
+    next_callback_id: AtomicU64,
+    pending_callbacks: Mutex<HashMap<u64, ResourceArc<CallbackTokenResource>>>,
+    /// Whether actors started on the runtime may be profiled
+    guest_profiling: bool,
+    /// Whether the engine meters fuel, which every instance then has to be given before it is called
+    fuel_metering: bool,
+    /// Advances the engine's epoch while guests run, `None` if the engine doesn't interrupt guests
+    /// (neither profiling nor interruptible calls are enabled)
+    epoch_ticker: Option<EpochTicker>,
+}
+
+impl RuntimeState {
+    fn new(
+        log_queue: LogQueue,
+        numbergen: Numbergen,
+        guest_profiling: bool,
+        fuel_metering: bool,
+        epoch_ticker: Option<EpochTicker>,
+    ) -> RuntimeState {
+        RuntimeState {
+            log_queue,
+            numbergen,
+            guest_profiling,
+            fuel_metering,
+            epoch_ticker,
+            builtins: BuiltinRegistry::default(),
+            local_actors: Mutex::new(LocalActors::default()),
+            draining: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            actor_stats: Mutex::new(HashMap::new()),
+            calls: AtomicU64::new(0),
+            call_errors: AtomicU64::new(0),
+            calls_cancelled: AtomicU64::new(0),
+            next_callback_id: AtomicU64::new(0),
+            pending_callbacks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a callback token that stays registered with the runtime (so that it can be released on
+    /// shutdown) for as long as the returned `PendingCallback` is alive
+    fn new_callback_token(&self) -> anyhow::Result<PendingCallback<'_>> {
+        if self.closed.load(Ordering::SeqCst) {
+            bail!("runtime is shut down, host calls are no longer accepted");
+        }
+        let id = self.next_callback_id.fetch_add(1, Ordering::SeqCst);
+        let (token, receiver) = CallbackToken::new();
+        let token = ResourceArc::new(CallbackTokenResource { token });
+        self.pending_callbacks
+            .lock()
+            .unwrap()
+            .insert(id, token.clone());
+        Ok(PendingCallback {
+            state: self,
+            id,
+            token,
+            receiver,
+        })
+    }
+
+    /// Updates the stats of an actor, forgetting the actor once it has no instances or calls left
+    fn update_actor_stats(&self, public_key: &str, update: impl FnOnce(&mut ActorStats)) {
+        let mut actor_stats = self.actor_stats.lock().unwrap();
+        let stats = actor_stats.entry(public_key.to_string()).or_default();
+        update(stats);
+        if stats.instances == 0 && stats.in_flight == 0 && stats.queued == 0 {
+            actor_stats.remove(public_key);
+        }
+    }
+
+    fn stats(&self) -> RuntimeStats {
+        let per_actor = self.actor_stats.lock().unwrap().clone();
+        let in_flight = self.in_flight.load(Ordering::SeqCst) as u64;
+        let pending_callbacks = self.pending_callbacks.lock().unwrap().len() as u64;
+        let workers = *crate::TOKIO_WORKERS as u64;
+        let executing = in_flight.saturating_sub(pending_callbacks).min(workers);
+        RuntimeStats {
+            actors: per_actor.values().map(|a| a.instances).sum(),
+            per_actor,
+            in_flight,
+            queued: self.queued.load(Ordering::SeqCst) as u64,
+            pending_callbacks,
+            workers,
+            estimated_utilisation: executing as f64 / workers as f64,
+            calls: self.calls.load(Ordering::SeqCst),
+            call_errors: self.call_errors.load(Ordering::SeqCst),
+            calls_cancelled: self.calls_cancelled.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Fails every callback still waiting on the Elixir host and refuses any new ones
+    fn release_callbacks(&self, reason: &str) {
+        self.closed.store(true, Ordering::SeqCst);
+        for (_, callback) in self.pending_callbacks.lock().unwrap().drain() {
+            callback.token.release(reason);
+        }
+    }
+}
+
+/// A callback token registered with the runtime for as long as a host call is waiting on it
+struct PendingCallback<'a> {
+    state: &'a RuntimeState,
+    id: u64,
+    token: ResourceArc<CallbackTokenResource>,
+    receiver: oneshot::Receiver<CallbackResult>,
+}
+
+impl PendingCallback<'_> {
+    /// Awaits the result of the callback for at most `timeout`, returning `None` if it never arrived.
+    /// This yields the tokio worker rather than blocking it, so waiting host calls don't limit how
+    /// many actor calls can make progress
+    async fn wait(&mut self, timeout: Duration) -> Option<CallbackResult> {
+        match tokio::time::timeout(timeout, &mut self.receiver).await {
+            Ok(Ok(result)) => Some(result),
+            // the sender can only go away without a result if the token was dropped unanswered
+            Ok(Err(_)) | Err(_) => None,
+        }
+    }
+}
+
+impl Drop for PendingCallback<'_> {
+    fn drop(&mut self) {
+        self.state
+            .pending_callbacks
+            .lock()
+            .unwrap()
+            .remove(&self.id);
+    }
+}
+
+/// Counts a call as in flight on both the actor and its runtime until dropped. This is dropped along
+/// with the call's task, so aborted calls are accounted for too
+struct InFlightCall {
+    actor: ResourceArc<ActorResource>,
+}
+
+impl InFlightCall {
+    fn new(actor: ResourceArc<ActorResource>) -> InFlightCall {
+        actor.in_flight.fetch_add(1, Ordering::SeqCst);
+        actor.runtime.in_flight.fetch_add(1, Ordering::SeqCst);
+        actor
+            .runtime
+            .update_actor_stats(&actor.public_key, |stats| stats.in_flight += 1);
+        InFlightCall { actor }
+    }
+}
+
+impl Drop for InFlightCall {
+    fn drop(&mut self) {
+        self.actor.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.actor.runtime.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.actor
+            .runtime
+            .update_actor_stats(&self.actor.public_key, |stats| {
+                stats.in_flight = stats.in_flight.saturating_sub(1)
+            });
+    }
+}
+
+/// Actor instances registered by the Elixir host as running, keyed by public key, to which host calls
+/// from other actors on the same runtime can be delivered without leaving the NIF. Instances hold a
+/// reference back to the runtime, so they must be removed (which draining does) to be freed
+#[derive(Default)]
+struct LocalActors {
+    instances: HashMap<String, Vec<ResourceArc<ActorResource>>>,
+    call_aliases: HashMap<String, String>,
+}
+
+impl LocalActors {
+    fn register(&mut self, actor: ResourceArc<ActorResource>) {
+        if let Some(call_alias) = &actor.call_alias {
+            self.call_aliases
+                .insert(call_alias.clone(), actor.public_key.clone());
+        }
+        let instances = self.instances.entry(actor.public_key.clone()).or_default();
+        if !instances.iter().any(|i| std::ptr::eq(&**i, &*actor)) {
+            instances.push(actor);
+        }
+    }
+
+    fn unregister(&mut self, actor: &ActorResource) -> bool {
+        let Some(instances) = self.instances.get_mut(&actor.public_key) else {
+            return false;
+        };
+        let before = instances.len();
+        instances.retain(|i| !std::ptr::eq(&**i, actor));
+        let removed = instances.len() != before;
+        if instances.is_empty() {
+            self.instances.remove(&actor.public_key);
+            self.call_aliases.retain(|_, pk| *pk != actor.public_key);
+        }
+        removed
+    }
+
+    /// Finds the least busy instance able to take a call addressed to a public key or call alias
+    fn resolve(&self, target: &str) -> Option<ResourceArc<ActorResource>> {
+        let public_key = self
+            .call_aliases
+            .get(target)
+            .map(String::as_str)
+            .unwrap_or(target);
+        self.instances
+            .get(public_key)?
+            .iter()
+            .filter(|i| !i.draining.load(Ordering::SeqCst))
+            .min_by_key(|i| i.in_flight.load(Ordering::SeqCst))
+            .cloned()
+    }
+}
+
+/// Reported to the Elixir host for every call delivered directly between local actors, in place of
+/// the invocation event the RPC path would have published. `encoded_claims` is the signed
+/// anti-forgery token of the invocation
+#[derive(NifMap)]
+pub struct LocalInvocationAudit {
+    id: String,
+    origin: String,
+    target: String,
+    operation: String,
+    bytes: u64,
+    encoded_claims: String,
+    error: Option<String>,
+}
+
+/// Fuel every call gets on a runtime metering fuel. Metering is for accounting rather than limiting, so
+/// this is as much as an instance can hold
+const FUEL_PER_CALL: u64 = i64::MAX as u64;
+
+/// Capability ID actors must claim to use the builtin logging capability
+const CAPABILITY_BUILTIN_LOGGING: &str = "wasmcloud:builtin:logging";
+/// Capability ID actors must claim to use the builtin numbergen capability
+const CAPABILITY_BUILTIN_NUMBERGEN: &str = "wasmcloud:builtin:numbergen";
+
+tokio::task_local! {
+    /// The actor call being executed by the current task, so that host calls made during the call
+    /// can tell which invocation they belong to
+    static CURRENT_CALL: CallScope;
+}
+
+/// Identifies an actor call for the duration of its execution
+struct CallScope {
+    invocation_id: String,
+    operation: String,
+    counters: Arc<CallCounters>,
+    /// Set once the call is cancelled, which has the guest trap at its next epoch check
+    cancelled: Arc<AtomicBool>,
+}
+
+/// The cancellation flag of the call being executed by the current task. A call made outside of any
+/// scope gets a flag of its own that is never set
+fn current_call_cancellation() -> Arc<AtomicBool> {
+    CURRENT_CALL
+        .try_with(|call| call.cancelled.clone())
+        .unwrap_or_default()
+}
+
+/// The counters of the call being executed by the current task. A call made outside of any scope
+/// gets counters of its own that nobody reads
+fn current_call_counters() -> Arc<CallCounters> {
+    CURRENT_CALL
+        .try_with(|call| call.counters.clone())
+        .unwrap_or_default()
+}
+
+/// The invocation ID and operation of the call being executed by the current task, if any
+fn current_call_ids() -> (Option<String>, Option<String>) {
+    CURRENT_CALL
+        .try_with(|call| (call.invocation_id.clone(), call.operation.clone()))
+        .map(|(id, op)| (Some(id), Some(op)))
+        .unwrap_or_default()
+}
+
+/// Whether the namespace of a host call addresses another actor, either by public key or by call
+/// alias, rather than a capability contract. Contract IDs always contain a `:`, whereas actor public
+/// keys and call aliases never do
+fn is_actor_target(namespace: &str) -> bool {
+    !namespace.contains(':')
+}
+
+/// A handle to a single in-flight actor invocation. This is returned to Elixir from `call_actor` so
+/// that the caller can abandon the call with `cancel_actor_call`
+pub struct ActorCallResource {
+    task: Mutex<Option<AbortHandle>>,
+    /// Shared with the call's scope, so that a guest that never yields can still be interrupted
+    cancelled: Arc<AtomicBool>,
+    reply: Arc<PendingReply>,
+    runtime: Arc<RuntimeState>,
+}
+
+/// The plumbing required to answer the original `from` of an actor call. Whichever of the call
+/// task or a cancellation takes this first is the only one that replies
+struct PendingReply {
+    pid: LocalPid,
+    inner: Mutex<Option<(OwnedEnv, SavedTerm)>>,
+}
+
+impl PendingReply {
+    fn take(&self) -> Option<(OwnedEnv, SavedTerm)> {
+        self.inner.lock().unwrap().take()
+    }
 }
 
 #[derive(NifStruct)]
 #[module = "HostCore.WasmCloud.Runtime.Config"]
 pub struct ExRuntimeConfig {
     host_id: String,
+    callback_timeout_ms: u64,
+    log_queue_capacity: usize,
+    log_overflow: LogOverflow,
+    numbergen_seed: Option<u64>,
+    builtin_keyvalue: bool,
+    local_actor_calls: bool,
+    cluster_seed: String,
+    cluster_issuers: Vec<String>,
+    module_cache_dir: Option<String>,
+    wasi_sandbox_dir: Option<String>,
+    allowed_imports: Option<Vec<ImportRule>>,
+    denied_imports: Vec<ImportRule>,
+    opt_level: OptLevel,
+    parallel_compilation: bool,
+    pooling_allocator: bool,
+    max_wasm_stack: Option<usize>,
+    wasm_proposals: Option<Vec<WasmProposal>>,
+    guest_profiling: bool,
+    interruptible_calls: bool,
+    fuel_metering: bool,
 }
 
 pub struct ElixirHandler {
@@ -43,68 +793,212 @@ pub struct ElixirHandler {
     pid: LocalPid,
     #[allow(unused)]
     host_id: String,
+    /// How long a host call may wait on the Elixir host before failing
+    callback_timeout: Duration,
+    /// Signs the invocations of direct calls between local actors, `None` when those are disabled
+    cluster_key: Option<KeyPair>,
+    /// Issuers whose signed invocations are accepted, which the local invocations are checked against
+    cluster_issuers: Vec<String>,
+    state: Arc<RuntimeState>,
 }
 
-#[async_trait]
-impl Handle<capability::Invocation> for ElixirHandler {
-    async fn handle(
+impl ElixirHandler {
+    /// Checks that the actor's claims include the given capability, so that unauthorized host calls
+    /// are rejected before they ever cross the NIF boundary
+    fn authorize(&self, claims: &jwt::Claims<jwt::Actor>, capability: &str) -> anyhow::Result<()> {
+        let authorized = claims
+            .metadata
+            .as_ref()
+            .and_then(|m| m.caps.as_ref())
+            .map(|caps| caps.iter().any(|c| c == capability))
+            .unwrap_or_default();
+        if authorized {
+            Ok(())
+        } else {
+            bail!(
+                "Invocation not authorized: actor {} is missing capability claim for {capability}",
+                claims.subject
+            )
+        }
+    }
+
+    /// Queues a log line on behalf of an actor, tagged with the actor's identity and the call it is
+    /// serving. Logging is fire-and-forget, the actor never waits on the Elixir host for it
+    async fn write_actor_log(
         &self,
         claims: &jwt::Claims<jwt::Actor>,
-        binding: String,
-        invocation: capability::Invocation,
+        level: LogLevel,
+        text: String,
+    ) {
+        let (invocation_id, operation) = current_call_ids();
+        let metadata = ActorLogMetadata {
+            actor_id: claims.subject.clone(),
+            call_alias: claims.metadata.as_ref().and_then(|m| m.call_alias.clone()),
+            invocation_id,
+            operation,
+        };
+        self.state
+            .log_queue
+            .push(ActorLog {
+                level,
+                text,
+                metadata,
+            })
+            .await;
+    }
+
+    /// Performs the checks the Elixir host makes on an invocation arriving over the lattice before it
+    /// runs the target: anti-forgery validation of the signed invocation and the expiry of the calling
+    /// actor's claims. Policy isn't consulted, which is why the Elixir host turns direct calls off when a
+    /// policy service is configured
+    fn check_local_invocation(
+        &self,
+        claims: &jwt::Claims<jwt::Actor>,
+        invocation: &inv::Invocation,
+    ) -> anyhow::Result<()> {
+        invocation
+            .validate_antiforgery(self.cluster_issuers.clone())
+            .map_err(|e| anyhow!("Anti-forgery check failed: {e}"))?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if claims.expires.map_or(false, |expires| now > expires) {
+            bail!(
+                "Invocation not authorized: claims of actor {} have expired",
+                claims.subject
+            );
+        }
+        Ok(())
+    }
+
+    /// Reports a direct call between local actors to Elixir, which publishes the usual invocation events
+    fn audit_local_invocation(&self, invocation: inv::Invocation, error: Option<String>) {
+        let audit = LocalInvocationAudit {
+            id: invocation.id,
+            origin: invocation.origin.public_key,
+            target: invocation.target.public_key,
+            operation: invocation.operation,
+            bytes: invocation.content_length.unwrap_or_default(),
+            encoded_claims: invocation.encoded_claims,
+            error,
+        };
+        send_to_pid(&self.pid, |env| {
+            (atoms::local_actor_invocation(), audit).encode(env)
+        });
+    }
+
+    /// Delivers a host call straight to an actor instance on this runtime. The call is still wrapped
+    /// in an invocation signed with the cluster key, which is validated before the target runs and
+    /// reported to Elixir for auditing
+    async fn call_local_actor(
+        &self,
+        claims: &jwt::Claims<jwt::Actor>,
+        cluster_key: &KeyPair,
+        target: ResourceArc<ActorResource>,
+        operation: String,
+        payload: Vec<u8>,
         call_context: &Option<Vec<u8>>,
     ) -> anyhow::Result<Option<Vec<u8>>> {
-        match invocation {
-            capability::Invocation::Logging(LoggingInvocation::WriteLog { level, text }) => {
-                // note: current OTP host does not have a trace log level
-                let level = match level {
-                    logging::Level::Debug => "debug",
-                    logging::Level::Info => "info",
-                    logging::Level::Warn => "warn",
-                    logging::Level::Error => "error",
-                };
-                let callback_token = new_callback_token();
-
-                let mut msg_env = OwnedEnv::new();
-                msg_env.send_and_clear(&self.pid.clone(), |env| {
-                    (
-                        atoms::perform_actor_log(),
-                        crate::Claims::from(claims.clone()),
-                        level,
-                        text,
-                        callback_token.clone(),
-                    )
-                        .encode(env)
+        let mut invocation = inv::Invocation::new(
+            cluster_key,
+            WasmCloudEntity::actor(&claims.subject),
+            WasmCloudEntity::actor(&target.public_key),
+            &operation,
+            payload,
+        );
+        if let Err(e) = self.check_local_invocation(claims, &invocation) {
+            self.audit_local_invocation(invocation, Some(e.to_string()));
+            return Err(e);
+        }
+        let payload = std::mem::take(&mut invocation.msg);
+        // the target sees the calling actor and this invocation, the trace and deadline carry over
+        let call_context = match CallContext::from_host_call(call_context) {
+            Some(mut context) => {
+                context.caller = Some(CallerEntity {
+                    public_key: claims.subject.clone(),
+                    ..Default::default()
                 });
+                context.invocation_id = Some(invocation.id.clone());
+                context.encode()?
+            }
+            None => call_context.clone().unwrap_or_default(),
+        };
 
-                let mut result = callback_token.token.return_value.lock().unwrap();
-                while result.is_none() {
-                    result = callback_token.token.continue_signal.wait(result).unwrap();
-                }
+        let admission = ActorResource::admit(&target)
+            .ok_or_else(|| anyhow!("actor {} is busy", target.public_key))?;
+        let _running = admission.start().await;
+        // cancelling the calling actor's call also interrupts the actor it is waiting on
+        let scope = CallScope {
+            invocation_id: invocation.id.clone(),
+            operation: operation.clone(),
+            counters: Arc::default(),
+            cancelled: current_call_cancellation(),
+        };
+        let response = CURRENT_CALL
+            .scope(scope, target.call(operation, payload, call_context))
+            .await;
+        let result = match response {
+            Ok(Ok(payload)) => Ok(Some(payload.unwrap_or_default())),
+            Ok(Err(e)) => Err(anyhow!(e)),
+            Err(e) => Err(e),
+        };
 
-                // we don't actually care about the result from the host here
+        self.audit_local_invocation(
+            invocation,
+            result.as_ref().err().map(|e| e.root_cause().to_string()),
+        );
+        result
+    }
+}
+
+impl ElixirHandler {
+    async fn dispatch(
+        &self,
+        claims: &jwt::Claims<jwt::Actor>,
+        binding: String,
+        invocation: capability::Invocation,
+        call_context: &Option<Vec<u8>>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        match &invocation {
+            capability::Invocation::Logging(_) => {
+                self.authorize(claims, CAPABILITY_BUILTIN_LOGGING)?
+            }
+            capability::Invocation::Numbergen(_) => {
+                self.authorize(claims, CAPABILITY_BUILTIN_NUMBERGEN)?
+            }
+            // calls to other actors don't require a capability claim
+            capability::Invocation::Host(HostInvocation { namespace, .. })
+                if !is_actor_target(namespace) =>
+            {
+                self.authorize(claims, namespace)?
+            }
+            capability::Invocation::Host(_) => {}
+        }
 
+        match invocation {
+            capability::Invocation::Logging(LoggingInvocation::WriteLog { level, text }) => {
+                self.write_actor_log(claims, level.into(), text).await;
                 Ok(None)
             }
 
             capability::Invocation::Numbergen(NumbergenInvocation::GenerateGuid) => {
-                let mut buf = uuid::Bytes::default();
-                thread_rng().fill_bytes(&mut buf);
-                let guid = uuid::Builder::from_random_bytes(buf)
-                    .into_uuid()
-                    .to_string();
+                let guid = self.state.numbergen.guid(&claims.subject);
                 trace!("generated GUID: `{guid}`");
                 numbergen::serialize_response(&guid).map(Some)
             }
 
             capability::Invocation::Numbergen(NumbergenInvocation::RandomInRange { min, max }) => {
-                let v = thread_rng().gen_range(min..=max);
+                let v = self
+                    .state
+                    .numbergen
+                    .random_in_range(&claims.subject, min, max)?;
                 trace!("generated random u32 in range [{min};{max}]: {v}");
                 numbergen::serialize_response(&v).map(Some)
             }
 
             capability::Invocation::Numbergen(NumbergenInvocation::Random32) => {
-                let v: u32 = thread_rng().gen();
+                let v = self.state.numbergen.random32(&claims.subject);
                 trace!("generated random u32: {v}");
                 numbergen::serialize_response(&v).map(Some)
             }
@@ -114,30 +1008,53 @@ impl Handle<capability::Invocation> for ElixirHandler {
                 operation,
                 payload,
             }) => {
-                let mut msg_env = OwnedEnv::new();
-                let callback_token = new_callback_token();
-                msg_env.send_and_clear(&self.pid.clone(), |env| {
+                if let Some(builtin) = self.state.builtins.find(&namespace, &binding) {
+                    return builtin
+                        .handle(claims, &operation, payload.as_deref().unwrap_or_default())
+                        .await
+                        .map(Some);
+                }
+
+                if let (true, Some(cluster_key)) = (is_actor_target(&namespace), &self.cluster_key)
+                {
+                    // bound on its own so the lock is released before the call is awaited
+                    let target = self.state.local_actors.lock().unwrap().resolve(&namespace);
+                    if let Some(target) = target {
+                        return self
+                            .call_local_actor(
+                                claims,
+                                cluster_key,
+                                target,
+                                operation,
+                                payload.unwrap_or_default(),
+                                call_context,
+                            )
+                            .await;
+                    }
+                }
+
+                let mut callback = self.state.new_callback_token()?;
+                if !send_to_pid(&self.pid, |env| {
                     (
                         atoms::invoke_callback(),
                         crate::Claims::from(claims.clone()),
                         (binding, namespace, operation),
                         payload.unwrap_or_default(),
-                        call_context.clone().unwrap_or_default(),
-                        callback_token.clone(),
+                        CallContext::from_host_call(call_context),
+                        callback.token.clone(),
                     )
                         .encode(env)
-                });
-
-                let mut result = callback_token.token.return_value.lock().unwrap();
-                while result.is_none() {
-                    result = callback_token.token.continue_signal.wait(result).unwrap();
+                }) {
+                    bail!("runtime process is not alive, cannot perform host call");
                 }
-                match result
-                    .as_ref()
-                    .expect("expect callback token to contain a result")
-                {
-                    (true, payload) => Ok(Some(payload.clone())),
-                    (false, e) => {
+
+                match callback.wait(self.callback_timeout).await {
+                    None => bail!(
+                        "Host call timed out after {}ms waiting for a response",
+                        self.callback_timeout.as_millis()
+                    ),
+                    Some((true, payload)) => Ok(Some(payload)),
+                    Some((false, e)) => {
                         // TODO: verify whether we should return none here or use an Err
                         error!("Elixir callback threw an exception.");
                         let err_str = String::from_utf8(e.to_vec()).unwrap_or_else(|parse_err| {
@@ -151,37 +1068,152 @@ impl Handle<capability::Invocation> for ElixirHandler {
     }
 }
 
-// hint: make sure this is only ever called when we're going to await the condvar, otherwise we
-// could "leak" condvars
-fn new_callback_token() -> ResourceArc<CallbackTokenResource> {
-    ResourceArc::new(CallbackTokenResource {
-        token: CallbackToken {
-            continue_signal: Condvar::new(),
-            return_value: Mutex::new(None),
-        },
-    })
+#[async_trait]
+impl Handle<capability::Invocation> for ElixirHandler {
+    async fn handle(
+        &self,
+        claims: &jwt::Claims<jwt::Actor>,
+        binding: String,
+        invocation: capability::Invocation,
+        call_context: &Option<Vec<u8>>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let bytes_out = match &invocation {
+            capability::Invocation::Host(HostInvocation { payload, .. }) => {
+                payload.as_ref().map_or(0, Vec::len)
+            }
+            _ => 0,
+        };
+        let recorded = is_recording().then(|| (binding.clone(), HostRequest::of(&invocation)));
+        let result = self
+            .dispatch(claims, binding, invocation, call_context)
+            .await;
+        if let Some((binding, request)) = recorded {
+            record_host_call(binding, request, &result);
+        }
+        let bytes_in = match &result {
+            Ok(Some(response)) => response.len(),
+            _ => 0,
+        };
+        // host calls made outside of an actor call (e.g. during instantiation) aren't attributed
+        let _ = CURRENT_CALL.try_with(|call| call.counters.record_host_call(bytes_out, bytes_in));
+        result
+    }
 }
 
 pub fn on_load(env: Env) -> bool {
     rustler::resource!(RuntimeResource, env);
     rustler::resource!(ActorResource, env);
+    rustler::resource!(ActorCallResource, env);
     true
 }
 
 #[rustler::nif(name = "runtime_new")]
 pub fn new(
     env: rustler::Env<'_>,
-    ExRuntimeConfig { host_id }: ExRuntimeConfig,
+    ExRuntimeConfig {
+        host_id,
+        callback_timeout_ms,
+        log_queue_capacity,
+        log_overflow,
+        numbergen_seed,
+        builtin_keyvalue,
+        local_actor_calls,
+        cluster_seed,
+        cluster_issuers,
+        module_cache_dir,
+        wasi_sandbox_dir,
+        allowed_imports,
+        denied_imports,
+        opt_level,
+        parallel_compilation,
+        pooling_allocator,
+        max_wasm_stack,
+        wasm_proposals,
+        guest_profiling,
+        interruptible_calls,
+        fuel_metering,
+    }: ExRuntimeConfig,
 ) -> Result<ResourceArc<RuntimeResource>, rustler::Error> {
+    let mut wasmtime_config = EngineConfig::default();
+    let engine_settings = EngineSettings {
+        opt_level,
+        parallel_compilation,
+        pooling_allocator,
+        max_wasm_stack,
+        wasm_proposals,
+        guest_profiling,
+        interruptible_calls,
+        fuel_metering,
+    };
+    let mut effective_config = engine_settings
+        .apply(&mut wasmtime_config)
+        .map_err(|e| Error::Term(Box::new(format!("invalid engine configuration: {e}"))))?;
+
+    let cluster_key = if local_actor_calls {
+        let key = KeyPair::from_seed(&cluster_seed)
+            .map_err(|e| Error::Term(Box::new(format!("invalid cluster seed: {e}"))))?;
+        Some(key)
+    } else {
+        None
+    };
+    let state = Arc::new(RuntimeState::new(
+        LogQueue::start(env.pid(), log_queue_capacity, log_overflow),
+        Numbergen::new(numbergen_seed),
+        guest_profiling,
+        fuel_metering,
+        engine_settings.epoch_interruption().then(EpochTicker::new),
+    ));
+    if builtin_keyvalue {
+        state
+            .builtins
+            .register(CONTRACT_KEYVALUE, None, Arc::new(KeyValueStore::default()));
+    }
     let handler: Box<dyn Handle<capability::Invocation>> = Box::new(ElixirHandler {
         pid: env.pid(),
         host_id,
+        callback_timeout: Duration::from_millis(callback_timeout_ms),
+        cluster_key,
+        cluster_issuers,
+        state: state.clone(),
+    });
+    // A cache that can't be set up only costs start times, so the runtime carries on compiling every
+    // module from its bytes rather than failing to start
+    let module_cache = module_cache_dir.as_deref().and_then(|dir| {
+        ModuleCache::open(dir)
+            .and_then(|cache| {
+                wasmtime_config.cache_config_load(cache.engine_config_file())?;
+                Ok(cache)
+            })
+            .map_err(|e| warn!("module cache disabled: {e:#}"))
+            .ok()
     });
-    let rt = WcRuntime::new(handler)
+    let rt = WcRuntime::builder(handler)
+        .engine_config(wasmtime_config)
+        .build()
         .context("failed to construct runtime")
         .map_err(|e| Error::Term(Box::new(e.to_string())))?;
+    if let Some(ticker) = &state.epoch_ticker {
+        ticker
+            .start(rt.engine().clone())
+            .context("failed to start the epoch ticker")
+            .map_err(|e| Error::Term(Box::new(e.to_string())))?;
+    }
+    let module_cache = module_cache.map(|cache| {
+        cache.for_runtime(
+            rt.version().to_string(),
+            effective_config.compilation_fingerprint(),
+        )
+    });
+    effective_config.module_cache_dir = module_cache_dir.filter(|_| module_cache.is_some());
 
-    let resource = ResourceArc::new(RuntimeResource { inner: rt });
+    let resource = ResourceArc::new(RuntimeResource {
+        inner: rt,
+        state,
+        module_cache,
+        engine_config: effective_config,
+        wasi_sandbox_dir: wasi_sandbox_dir.map(PathBuf::from),
+        import_policy: ImportPolicy::new(allowed_imports, denied_imports),
+    });
     Ok(resource)
 }
 
@@ -192,6 +1224,60 @@ pub fn version(runtime_resource: ResourceArc<RuntimeResource>) -> Result<String,
     Ok(v.to_string())
 }
 
+/// Reports the configuration the runtime's engine is running with, including the defaults of settings
+/// left unset
+#[rustler::nif(name = "engine_config")]
+pub fn engine_config(runtime_resource: ResourceArc<RuntimeResource>) -> EffectiveEngineConfig {
+    runtime_resource.engine_config.clone()
+}
+
+/// Reports how the runtime's actor log queue has coped with the volume of actor logging
+#[rustler::nif(name = "actor_log_stats")]
+pub fn actor_log_stats(runtime_resource: ResourceArc<RuntimeResource>) -> LogQueueStats {
+    runtime_resource.state.log_queue.stats()
+}
+
+/// Reports how loaded the runtime is, for saturation reporting and scheduling decisions
+#[rustler::nif(name = "runtime_stats")]
+pub fn runtime_stats(runtime_resource: ResourceArc<RuntimeResource>) -> RuntimeStats {
+    runtime_resource.state.stats()
+}
+
+/// Reports the modules in the runtime's compiled-module cache, `nil` if it runs without one
+#[rustler::nif(name = "module_cache_stats", schedule = "DirtyIo")]
+pub fn module_cache_stats(
+    runtime_resource: ResourceArc<RuntimeResource>,
+) -> Option<ModuleCacheStats> {
+    runtime_resource
+        .module_cache
+        .as_ref()
+        .map(ModuleCache::stats)
+}
+
+/// Empties the runtime's compiled-module cache, returning how many modules were in it
+#[rustler::nif(name = "purge_module_cache", schedule = "DirtyIo")]
+pub fn purge_module_cache(
+    runtime_resource: ResourceArc<RuntimeResource>,
+) -> Result<usize, rustler::Error> {
+    let Some(cache) = &runtime_resource.module_cache else {
+        return Ok(0);
+    };
+    cache
+        .purge()
+        .map_err(|e| Error::Term(Box::new(format!("{e:#}"))))
+}
+
+/// Replaces the seed used by the builtin numbergen capability, restarting every actor's sequence. A
+/// `nil` seed goes back to drawing from the thread RNG
+#[rustler::nif(name = "reseed_numbergen")]
+pub fn reseed_numbergen(
+    runtime_resource: ResourceArc<RuntimeResource>,
+    seed: Option<u64>,
+) -> rustler::Atom {
+    runtime_resource.state.numbergen.reseed(seed);
+    atoms::ok()
+}
+
 /// Called from the Elixir native wrapper which is in turn wrapped by the Wasmcloud.Runtime.Server GenServer
 #[rustler::nif(name = "start_actor")]
 #[allow(unused_variables)]
@@ -199,16 +1285,152 @@ pub fn start_actor<'a>(
     env: rustler::Env<'a>,
     runtime_resource: ResourceArc<RuntimeResource>,
     bytes: Binary<'a>,
+    ExActorConfig {
+        max_concurrency,
+        max_queued,
+        wasi,
+    }: ExActorConfig,
 ) -> Result<ResourceArc<ActorResource>, rustler::Error> {
-    let actor = Actor::new(&runtime_resource.inner, bytes.as_slice())
-        .context("failed to load actor from bytes")
-        .unwrap();
+    if max_concurrency == Some(0) {
+        return Err(Error::Term(Box::new(
+            "max_concurrency must allow at least one call",
+        )));
+    }
+
+    if runtime_resource.state.draining.load(Ordering::SeqCst) {
+        return Err(Error::Term(Box::new(
+            "runtime is shutting down, no new actors can be started",
+        )));
+    }
+
+    let wasi = wasi
+        .map(|wasi| wasi.resolve(runtime_resource.wasi_sandbox_dir.as_deref()))
+        .transpose()
+        .map_err(|e| Error::Term(Box::new(format!("invalid WASI configuration: {e:#}"))))?;
+
+    let version = load_actor(
+        &runtime_resource,
+        bytes.as_slice(),
+        ActorIdentity::of(bytes.as_slice()),
+    )?;
+    let public_key = version.identity.public_key();
+    let call_alias = version.identity.call_alias();
+
+    runtime_resource
+        .state
+        .update_actor_stats(&public_key, |stats| stats.instances += 1);
 
-    let ar = ActorResource { actor };
+    let ar = ActorResource {
+        version: RwLock::new(Arc::new(version)),
+        public_key,
+        call_alias,
+        runtime: runtime_resource.state.clone(),
+        draining: AtomicBool::new(false),
+        in_flight: AtomicUsize::new(0),
+        concurrency: max_concurrency.map(|n| Arc::new(Semaphore::new(n))),
+        max_queued,
+        queued: AtomicUsize::new(0),
+        wasi,
+        recorder: Mutex::new(None),
+        profiler: Mutex::new(None),
+    };
 
     Ok(ResourceArc::new(ar))
 }
 
+/// Checks a module against the runtime's import policy and compiles it
+fn load_actor(
+    runtime_resource: &RuntimeResource,
+    bytes: &[u8],
+    identity: ActorIdentity,
+) -> NifResult<ActorVersion> {
+    if !runtime_resource.import_policy.is_open() {
+        let info = inspect(bytes)
+            .map_err(|e| Error::Term(Box::new(format!("failed to inspect module: {e:#}"))))?;
+        runtime_resource
+            .import_policy
+            .check(&info.imports)
+            .map_err(|e| Error::Term(Box::new(e)))?;
+    }
+
+    // The runtime takes both core modules using the wasmbus ABI and components, whose imports reach the
+    // same handler. With a module cache configured the engine loads the compiled artifact from it when
+    // there is a compatible one, and compiles the module and stores the result otherwise
+    let actor = Actor::new(&runtime_resource.inner, bytes)
+        .context("failed to load actor from bytes")
+        .map_err(|e| Error::Term(Box::new(format!("{e:#}"))))?;
+    if let Some(cache) = &runtime_resource.module_cache {
+        cache.record(bytes, &identity.public_key());
+    }
+    Ok(ActorVersion {
+        actor,
+        identity,
+        sha256: module_sha256(bytes),
+    })
+}
+
+/// Replaces the module behind a running actor with a newer revision of the same actor, keeping its
+/// configuration, registration and stats. The replacement must be signed by the same issuer for the same
+/// subject, have a higher revision, use the same ABI and keep the call alias. Calls already in flight
+/// finish on the module they started with, every call after the swap runs the new one
+#[rustler::nif(name = "update_actor", schedule = "DirtyCpu")]
+pub fn update_actor<'a>(
+    runtime_resource: ResourceArc<RuntimeResource>,
+    component: ResourceArc<ActorResource>,
+    bytes: Binary<'a>,
+) -> NifResult<rustler::Atom> {
+    if !Arc::ptr_eq(&runtime_resource.state, &component.runtime) {
+        return Err(Error::Term(Box::new(
+            "actor was not started on this runtime",
+        )));
+    }
+    if component.draining.load(Ordering::SeqCst) {
+        return Err(Error::Term(Box::new(
+            "actor is draining and can no longer be updated",
+        )));
+    }
+
+    // checked before compiling, so that an incompatible module is turned away cheaply
+    let identity = ActorIdentity::of(bytes.as_slice());
+    component
+        .current()
+        .identity
+        .check_update(&identity)
+        .map_err(|e| Error::Term(Box::new(e)))?;
+    let next = load_actor(&runtime_resource, bytes.as_slice(), identity)?;
+
+    // and again with the lock held, in case another update got in while compiling
+    let mut version = component.version.write().unwrap();
+    version
+        .identity
+        .check_update(&next.identity)
+        .map_err(|e| Error::Term(Box::new(e)))?;
+    *version = Arc::new(next);
+
+    Ok(atoms::ok())
+}
+
+/// Makes a running actor instance reachable by direct calls from other actors on its runtime. This has no
+/// effect on routing unless the runtime was configured with `local_actor_calls`
+#[rustler::nif(name = "register_local_actor")]
+pub fn register_local_actor(component: ResourceArc<ActorResource>) -> rustler::Atom {
+    let runtime = component.runtime.clone();
+    runtime.local_actors.lock().unwrap().register(component);
+    atoms::ok()
+}
+
+/// Stops delivering direct calls to an actor instance, e.g. when it is replaced by a live update.
+/// Draining an actor also unregisters it
+#[rustler::nif(name = "unregister_local_actor")]
+pub fn unregister_local_actor(component: ResourceArc<ActorResource>) -> bool {
+    component
+        .runtime
+        .local_actors
+        .lock()
+        .unwrap()
+        .unregister(&component)
+}
+
 // This does not need to be on a dirty scheduler as it simply spawns a TOKIO
 // task and returns, never taking more than a millisecond
 #[rustler::nif(name = "call_actor")]
@@ -218,8 +1440,20 @@ pub fn call_actor<'a>(
     operation: &str,
     payload: Binary<'a>,
     call_context: Binary<'a>,
+    invocation_id: String,
     from: Term,
-) -> rustler::Atom {
+) -> NifResult<(rustler::Atom, ResourceArc<ActorCallResource>)> {
+    if component.draining.load(Ordering::SeqCst)
+        || component.runtime.draining.load(Ordering::SeqCst)
+    {
+        return Err(Error::Term(Box::new(
+            "actor is draining and no longer accepts calls",
+        )));
+    }
+    let Some(admission) = ActorResource::admit(&component) else {
+        return Err(Error::Term(Box::new(atoms::busy())));
+    };
+
     let pid = env.pid();
     let mut thread_env = OwnedEnv::new();
 
@@ -228,30 +1462,110 @@ pub fn call_actor<'a>(
     let operation = operation.to_owned();
     let call_context = call_context.to_vec();
 
+    let reply = Arc::new(PendingReply {
+        pid,
+        inner: Mutex::new(Some((thread_env, from))),
+    });
+    let task_reply = reply.clone();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let task_cancelled = cancelled.clone();
+    let runtime = component.runtime.clone();
+    runtime.calls.fetch_add(1, Ordering::SeqCst);
+
     // ref: https://github.com/tessi/wasmex/issues/256
     // here we spawn a TOKIO task, do the work of the actor invocation,
     // and use other sync mechanisms to finish the work and send
     // the results to the caller (the `from` field)
 
-    crate::spawn(async move {
-        let response = component
-            .actor
-            .call_with_context(operation, Some(payload), call_context)
-            .await;
-        thread_env.send_and_clear(&pid, |thread_env| {
-            send_actor_call_response(thread_env, from, response)
-        });
+    let handle = crate::spawn(async move {
+        let _running = admission.start().await;
+        let counters = Arc::new(CallCounters::default());
+        let scope = CallScope {
+            invocation_id,
+            operation: operation.clone(),
+            counters: counters.clone(),
+            cancelled: task_cancelled,
+        };
+        let bytes_in = payload.len();
+        let started = Instant::now();
+        let response = metrics::cpu_timed(
+            counters.clone(),
+            CURRENT_CALL.scope(scope, component.call(operation, payload, call_context)),
+        )
+        .await;
+        let bytes_out = match &response {
+            Ok(Ok(Some(data))) => data.len(),
+            _ => 0,
+        };
+        let metrics = counters.finish(started.elapsed(), bytes_in, bytes_out);
+        if !matches!(response, Ok(Ok(_))) {
+            component.runtime.call_errors.fetch_add(1, Ordering::SeqCst);
+        }
+        // a cancellation may have already replied on our behalf
+        if let Some((mut thread_env, from)) = task_reply.take() {
+            thread_env.send_and_clear(&task_reply.pid, |thread_env| {
+                send_actor_call_response(thread_env, from, response, metrics)
+            });
+        }
     });
 
-    // the Elixir host doesn't get this right away because it returned `:noreply`, allowing some other process
-    // to reply on its behalf
-    atoms::ok()
+    let call = ActorCallResource {
+        task: Mutex::new(Some(handle.abort_handle())),
+        cancelled,
+        reply,
+        runtime,
+    };
+
+    // the Elixir host doesn't get the result right away because it returned `:noreply`, allowing some other
+    // process to reply on its behalf. The call reference lets it cancel the invocation in the meantime
+    Ok((atoms::ok(), ResourceArc::new(call)))
+}
+
+/// Aborts the task backing an in-flight actor call. A guest waiting on the host is dropped along with
+/// the task. On a runtime with interruptible calls (or guest profiling) enabled, a guest that is still
+/// running traps at its next epoch check, even in a loop that never calls the host; otherwise it is
+/// only stopped the next time it yields to the host. If the call had not already completed, the
+/// original caller receives `{:error, :cancelled}`
+#[rustler::nif(name = "cancel_actor_call")]
+pub fn cancel_actor_call(call: ResourceArc<ActorCallResource>) -> rustler::Atom {
+    call.cancelled.store(true, Ordering::SeqCst);
+    if let Some(task) = call.task.lock().unwrap().take() {
+        task.abort();
+    }
+
+    let reply = call.reply.clone();
+    if let Some((mut thread_env, from)) = reply.take() {
+        call.runtime.calls_cancelled.fetch_add(1, Ordering::SeqCst);
+        // sending from an owned env is not permitted on a scheduler thread, so let tokio do it
+        crate::spawn(async move {
+            thread_env.send_and_clear(&reply.pid, |thread_env| {
+                let from = from
+                    .load(thread_env)
+                    .decode::<Term>()
+                    .unwrap_or_else(|_| "could not load 'from' param".encode(thread_env));
+                make_tuple(
+                    thread_env,
+                    &[
+                        atoms::returned_function_call().encode(thread_env),
+                        (atoms::error(), atoms::cancelled()).encode(thread_env),
+                        from,
+                    ],
+                )
+            });
+        });
+        atoms::ok()
+    } else {
+        atoms::already_completed()
+    }
 }
 
+/// Produces `{:returned_function_call, {:ok, payload, metrics}, from}` for a call that completed, or
+/// `{:returned_function_call, {:error, msg}, from}` for one that failed
 fn send_actor_call_response(
     thread_env: Env,
     from: SavedTerm,
     response: anyhow::Result<Result<Option<Vec<u8>>, String>>,
+    metrics: CallMetrics,
 ) -> Term {
     let from = from
         .load(thread_env)
@@ -259,21 +1573,26 @@ fn send_actor_call_response(
         .unwrap_or_else(|_| "could not load 'from' param".encode(thread_env));
 
     match response {
-        Ok(opt_data) => {
-            // Ultimately sends {:ok, payload} once the envelopes are removed
-            let data = opt_data.unwrap_or_default();
+        Ok(Ok(data)) => {
+            // Ultimately sends {:ok, payload, metrics} once the envelopes are removed
             make_tuple(
                 thread_env,
                 &[
                     atoms::returned_function_call().encode(thread_env),
                     make_tuple(
                         thread_env,
-                        &[atoms::ok().encode(thread_env), data.encode(thread_env)],
+                        &[
+                            atoms::ok().encode(thread_env),
+                            data.encode(thread_env),
+                            metrics.encode(thread_env),
+                        ],
                     ),
                     from,
                 ],
             )
         }
+        // the guest's own error, sent as {:error, msg} like a failed call
+        Ok(Err(e)) => make_error_tuple(&thread_env, e.as_str(), from),
         Err(e) => {
             let rc = e.root_cause().to_string();
             // Once the layers are removed, sends {:error, msg}
@@ -282,6 +1601,267 @@ fn send_actor_call_response(
     }
 }
 
+/// Stops an actor from accepting new calls and waits up to `timeout_ms` for its in-flight calls to finish
+#[rustler::nif(name = "drain_actor", schedule = "DirtyIo")]
+pub fn drain_actor(
+    component: ResourceArc<ActorResource>,
+    timeout_ms: u64,
+) -> NifResult<rustler::Atom> {
+    component.draining.store(true, Ordering::SeqCst);
+    component
+        .runtime
+        .local_actors
+        .lock()
+        .unwrap()
+        .unregister(&component);
+
+    crate::task::TOKIO.block_on(wait_for_idle(
+        || component.in_flight.load(Ordering::SeqCst) + component.queued.load(Ordering::SeqCst),
+        timeout_ms,
+    ))?;
+
+    Ok(atoms::ok())
+}
+
+/// Stops a runtime from accepting new actors or calls and waits up to `timeout_ms` for calls in flight
+/// on any of its actors to finish. Whether or not they finish in time, any host callbacks still waiting
+/// on the Elixir host are then released with an error, and subsequent host calls fail immediately
+#[rustler::nif(name = "drain_runtime", schedule = "DirtyIo")]
+pub fn drain_runtime(
+    runtime_resource: ResourceArc<RuntimeResource>,
+    timeout_ms: u64,
+) -> NifResult<rustler::Atom> {
+    let state = &runtime_resource.state;
+    state.draining.store(true, Ordering::SeqCst);
+
+    let drained = crate::task::TOKIO.block_on(wait_for_idle(
+        || state.in_flight.load(Ordering::SeqCst) + state.queued.load(Ordering::SeqCst),
+        timeout_ms,
+    ));
+    state.release_callbacks("runtime is shutting down");
+    // registered actors refer back to the runtime, so they have to go for it to be freed
+    *state.local_actors.lock().unwrap() = LocalActors::default();
+
+    drained.map(|_| atoms::ok())
+}
+
+/// Waits for `calls`, the number of queued and in-flight calls, to drop to zero
+async fn wait_for_idle(calls: impl Fn() -> usize, timeout_ms: u64) -> NifResult<()> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    loop {
+        let remaining = calls();
+        if remaining == 0 {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::Term(Box::new(format!(
+                "timed out waiting for {remaining} queued or in-flight call(s) to finish"
+            ))));
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Operation answered by every actor deriving `HealthResponder`
+const HEALTH_CHECK_OPERATION: &str = "HealthResponder.HealthRequest";
+
+#[derive(Serialize)]
+struct HealthCheckRequest {}
+
+#[derive(Deserialize)]
+struct HealthCheckResponse {
+    healthy: bool,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// The outcome of probing an actor's health
+#[derive(NifMap, Debug)]
+pub struct ActorHealth {
+    healthy: bool,
+    /// The actor's own message, or why it couldn't be asked
+    message: Option<String>,
+}
+
+impl ActorHealth {
+    fn unhealthy(message: impl Into<String>) -> ActorHealth {
+        ActorHealth {
+            healthy: false,
+            message: Some(message.into()),
+        }
+    }
+}
+
+/// Asks an actor for its health, giving it `timeout_ms` to answer once it holds one of the actor's
+/// concurrency slots, and sends the outcome to the calling process as `{:actor_health, health}`. An
+/// actor that fails the call, doesn't answer in time or is draining is reported as unhealthy. A busy
+/// actor is not an unhealthy one, so the probe waits for a slot like any other call, and returns
+/// `{:error, :busy}` without probing if the actor's queue is full
+#[rustler::nif(name = "health_check_actor")]
+pub fn health_check_actor(
+    env: rustler::Env,
+    component: ResourceArc<ActorResource>,
+    timeout_ms: u64,
+) -> NifResult<rustler::Atom> {
+    let draining = component.draining.load(Ordering::SeqCst)
+        || component.runtime.draining.load(Ordering::SeqCst);
+    let admission = if draining {
+        None
+    } else {
+        let Some(admission) = ActorResource::admit(&component) else {
+            return Err(Error::Term(Box::new(atoms::busy())));
+        };
+        Some(admission)
+    };
+
+    let pid = env.pid();
+    crate::spawn(async move {
+        let health = match admission {
+            Some(admission) => probe_health(component, admission, timeout_ms).await,
+            None => ActorHealth::unhealthy("actor is draining"),
+        };
+        OwnedEnv::new().send_and_clear(&pid, |env| (atoms::actor_health(), health).encode(env));
+    });
+    Ok(atoms::ok())
+}
+
+async fn probe_health(
+    component: ResourceArc<ActorResource>,
+    admission: Admission,
+    timeout_ms: u64,
+) -> ActorHealth {
+    let payload = match inv::serialize(HealthCheckRequest {}) {
+        Ok(payload) => payload,
+        Err(e) => return ActorHealth::unhealthy(format!("failed to encode health request: {e}")),
+    };
+
+    let running = admission.start().await;
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let task_cancelled = cancelled.clone();
+    // the call runs in a task of its own so that the deadline holds even while the guest never
+    // yields to the host
+    let task = crate::spawn(async move {
+        let _running = running;
+        let scope = CallScope {
+            invocation_id: uuid::Uuid::new_v4().to_string(),
+            operation: HEALTH_CHECK_OPERATION.to_string(),
+            counters: Arc::new(CallCounters::default()),
+            cancelled: task_cancelled,
+        };
+        CURRENT_CALL
+            .scope(
+                scope,
+                component.call(HEALTH_CHECK_OPERATION.to_string(), payload, Vec::new()),
+            )
+            .await
+    });
+    let abort = task.abort_handle();
+
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), task).await {
+        Err(_) => {
+            cancelled.store(true, Ordering::SeqCst);
+            abort.abort();
+            ActorHealth::unhealthy(format!("no health response within {timeout_ms}ms"))
+        }
+        Ok(Err(e)) => ActorHealth::unhealthy(format!("health check did not complete: {e}")),
+        Ok(Ok(Err(e))) => ActorHealth::unhealthy(format!("{e:#}")),
+        Ok(Ok(Ok(Err(e)))) => ActorHealth::unhealthy(e),
+        Ok(Ok(Ok(Ok(None)))) => ActorHealth::unhealthy("actor returned an empty health response"),
+        Ok(Ok(Ok(Ok(Some(data))))) => match inv::deserialize::<HealthCheckResponse>(&data) {
+            Ok(HealthCheckResponse { healthy, message }) => ActorHealth {
+                healthy,
+                message: message.filter(|m| !m.is_empty()),
+            },
+            Err(e) => ActorHealth::unhealthy(format!("invalid health response: {e}")),
+        },
+    }
+}
+
+/// Starts recording every call made on an actor to `path`, along with the results of the host calls it
+/// makes, so that the calls can be replayed later with `replay_recording`. The file is replaced if it
+/// exists
+#[rustler::nif(name = "start_recording", schedule = "DirtyIo")]
+pub fn start_recording(
+    component: ResourceArc<ActorResource>,
+    path: String,
+) -> NifResult<rustler::Atom> {
+    let mut recorder = component.recorder.lock().unwrap();
+    if recorder.is_some() {
+        return Err(Error::Term(Box::new("actor is already being recorded")));
+    }
+    let created = Recorder::create(
+        Path::new(&path),
+        &component.public_key,
+        &component.current().sha256,
+    )
+    .map_err(|e| Error::Term(Box::new(format!("{e:#}"))))?;
+    *recorder = Some(Arc::new(created));
+    Ok(atoms::ok())
+}
+
+/// Stops recording an actor's calls, returning how many were recorded. Calls still in flight are
+/// recorded when they finish
+#[rustler::nif(name = "stop_recording")]
+pub fn stop_recording(component: ResourceArc<ActorResource>) -> NifResult<usize> {
+    component
+        .recorder
+        .lock()
+        .unwrap()
+        .take()
+        .map(|recorder| recorder.calls())
+        .ok_or_else(|| Error::Term(Box::new("actor is not being recorded")))
+}
+
+/// Re-runs the calls of a recording on a module, which may be the recorded one or a new build of it,
+/// answering its host calls from the recording rather than from the Elixir host or any provider
+#[rustler::nif(name = "replay_recording", schedule = "DirtyCpu")]
+pub fn replay_recording(bytes: Binary, path: String) -> NifResult<ReplayReport> {
+    crate::record::replay(bytes.as_slice(), Path::new(&path))
+        .map_err(|e| Error::Term(Box::new(format!("failed to replay recording: {e:#}"))))
+}
+
+/// Starts sampling the guest stack of the next `calls` calls made on an actor. The profile is written
+/// to `path` by `stop_profiling`, in the Gecko format the Firefox profiler opens. Only actors on a
+/// runtime started with guest profiling enabled can be profiled
+#[rustler::nif(name = "start_profiling", schedule = "DirtyIo")]
+pub fn start_profiling(
+    component: ResourceArc<ActorResource>,
+    path: String,
+    calls: usize,
+) -> NifResult<rustler::Atom> {
+    if calls == 0 {
+        return Err(Error::Term(Box::new("must profile at least one call")));
+    }
+    if !component.runtime.guest_profiling {
+        return Err(Error::Term(Box::new(
+            "guest profiling is not enabled on the actor's runtime",
+        )));
+    }
+    let mut profiler = component.profiler.lock().unwrap();
+    if profiler.is_some() {
+        return Err(Error::Term(Box::new("actor is already being profiled")));
+    }
+    let created = Profiler::create(Path::new(&path), &component.public_key, calls)
+        .map_err(|e| Error::Term(Box::new(format!("{e:#}"))))?;
+    *profiler = Some(Arc::new(created));
+    Ok(atoms::ok())
+}
+
+/// Stops profiling an actor and writes out the samples taken, whether or not all the calls asked for
+/// were made
+#[rustler::nif(name = "stop_profiling", schedule = "DirtyIo")]
+pub fn stop_profiling(component: ResourceArc<ActorResource>) -> NifResult<ProfileSummary> {
+    let profiler = component
+        .profiler
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| Error::Term(Box::new("actor is not being profiled")))?;
+    profiler
+        .finish()
+        .map_err(|e| Error::Term(Box::new(format!("{e:#}"))))
+}
+
 /// Produces an Elixir tuple in the form {:error, reason} along with the `from` value propogated
 /// through the plumbing
 fn make_error_tuple<'a>(env: &Env<'a>, reason: &str, from: Term<'a>) -> Term<'a> {
@@ -303,9 +1883,67 @@ pub fn receive_callback_result(
     success: bool,
     binary_result: Binary<'_>,
 ) -> NifResult<rustler::Atom> {
-    let mut result = token_resource.token.return_value.lock().unwrap();
-    *result = Some((success, binary_result.to_vec()));
-    token_resource.token.continue_signal.notify_one();
+    token_resource
+        .token
+        .complete(success, binary_result.to_vec());
 
     Ok(atoms::ok())
 }
+
+#[cfg(test)]
+mod test {
+    use wascap::jwt::{Actor, Claims};
+
+    use super::{is_actor_target, ActorIdentity};
+    use crate::inspect::Abi;
+
+    fn identity(subject: &str, issuer: &str, rev: i32, abi: Abi) -> ActorIdentity {
+        ActorIdentity {
+            claims: Some(Claims::<Actor>::new(
+                "counter".to_string(),
+                issuer.to_string(),
+                subject.to_string(),
+                None,
+                None,
+                false,
+                Some(rev),
+                None,
+                None,
+            )),
+            abi,
+        }
+    }
+
+    #[test]
+    fn host_call_namespaces() {
+        assert!(is_actor_target(
+            "MCLOGOFFRJ4WK5XC6Z6WM7A3SAVHPHKDAVG6OMZVOOC6RQCN5IOKPTQL"
+        ));
+        assert!(is_actor_target("wasmcloud/pinger"));
+        assert!(!is_actor_target("wasmcloud:keyvalue"));
+        assert!(!is_actor_target("wasmcloud:builtin:logging"));
+    }
+
+    #[test]
+    fn live_updates_must_replace_an_older_revision_of_the_same_actor() {
+        let current = identity("MACTOR", "AISSUER", 2, Abi::Wasmbus);
+        assert!(current
+            .check_update(&identity("MACTOR", "AISSUER", 3, Abi::Wasmbus))
+            .is_ok());
+        for (next, reason) in [
+            (identity("MOTHER", "AISSUER", 3, Abi::Wasmbus), "actor"),
+            (identity("MACTOR", "AOTHER", 3, Abi::Wasmbus), "issued"),
+            (identity("MACTOR", "AISSUER", 2, Abi::Wasmbus), "revision"),
+            (identity("MACTOR", "AISSUER", 3, Abi::Component), "ABI"),
+            (
+                ActorIdentity {
+                    claims: None,
+                    abi: Abi::Wasmbus,
+                },
+                "claims",
+            ),
+        ] {
+            assert!(current.check_update(&next).unwrap_err().contains(reason));
+        }
+    }
+}
@@ -0,0 +1,13 @@
+;; An actor whose every call spins forever without yielding to the host, used to test that calls
+;; can still be interrupted
+(module
+  (import "wasmbus" "__guest_request" (func $guest_request (param i32 i32)))
+  (memory (export "memory") 1)
+
+  (func (export "__guest_call") (param $op_len i32) (param $msg_len i32) (result i32)
+    (loop $spin
+      (br $spin))
+    (i32.const 0))
+
+  (func (export "__wasmbus_rpc_version") (result i32)
+    (i32.const 1)))
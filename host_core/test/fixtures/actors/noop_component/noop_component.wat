@@ -0,0 +1,5 @@
+;; The smallest actor that is a component rather than a core module, used to test that components are
+;; started and have their claims read like any other actor
+(component
+  (core module $actor
+    (memory (export "memory") 1)))
@@ -0,0 +1,31 @@
+# common makefile rules for the test actors written in the WebAssembly text format
+#
+# Before including this, your project Makefile should define PROJECT, VERSION, REVISION and CLAIMS
+# (see actor.mk), and optionally ACTOR_ALIAS. The source is $(PROJECT).wat, which may be a core
+# module or a component, and the signed actor is written next to the other fixtures
+
+KEYDIR     ?= .keys
+WASH       ?= wash
+WASM_TOOLS ?= wasm-tools
+DIST_WASM  ?= ../$(PROJECT)_s.wasm
+UNSIGNED_WASM = build/$(PROJECT).wasm
+
+all:: $(DIST_WASM)
+
+$(UNSIGNED_WASM): $(PROJECT).wat
+	@mkdir -p $(dir $@)
+	$(WASM_TOOLS) parse $< -o $@
+
+$(DIST_WASM): $(UNSIGNED_WASM) Makefile
+	$(WASH) claims sign $< \
+		$(foreach claim,$(CLAIMS), -c $(claim) ) \
+		--name $(PROJECT) --ver $(VERSION) --rev $(REVISION) \
+		$(if $(ACTOR_ALIAS),--call-alias $(ACTOR_ALIAS)) \
+		--issuer $(KEYDIR)/account.nk --subject $(KEYDIR)/module.nk \
+		--destination $@
+
+inspect claims: $(DIST_WASM)
+	$(WASH) claims inspect $(DIST_WASM)
+
+clean::
+	rm -rf build
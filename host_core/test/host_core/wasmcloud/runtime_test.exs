@@ -0,0 +1,598 @@
+defmodule HostCore.WasmCloud.RuntimeTest do
+  # Load tests against the runtime shouldn't compete with other tests for the host's schedulers
+  use ExUnit.Case, async: false
+
+  import HostCoreTest.Common, only: [cleanup: 2, standard_setup: 1]
+
+  alias HostCore.WasmCloud.Native
+  alias HostCore.WasmCloud.Runtime
+  alias HostCore.WasmCloud.Runtime.ActorConfig
+  alias HostCore.WasmCloud.Runtime.CallContext
+  alias HostCore.WasmCloud.Runtime.Config, as: RuntimeConfig
+  alias HostCore.WasmCloud.Runtime.ImportRule
+  alias HostCore.WasmCloud.Runtime.Server, as: RuntimeServer
+  alias HostCore.WasmCloud.Runtime.WasiConfig
+
+  require OpenTelemetry.Tracer, as: Tracer
+
+  @kvcounter_path HostCoreTest.Constants.kvcounter_path()
+  @kvcounter_unpriv_path HostCoreTest.Constants.kvcounter_unpriv_path()
+  @randogenlogger_path HostCoreTest.Constants.randogenlogger_path()
+  @pinger_path HostCoreTest.Constants.pinger_path()
+  @looper_path HostCoreTest.Constants.looper_path()
+  @noop_component_path HostCoreTest.Constants.noop_component_path()
+  @noop_component_key HostCoreTest.Constants.noop_component_key()
+
+  describe "Invoking actors through the runtime" do
+    setup :standard_setup
+
+    test "many concurrent calls waiting on host calls all make progress", %{
+      :hconfig => config,
+      :host_pid => pid
+    } do
+      on_exit(fn -> cleanup(pid, config) end)
+
+      {:ok, runtime} = RuntimeServer.runtime_for_host(config.host_key)
+      aref = start_actor(runtime, @kvcounter_path)
+
+      # Every call waits on a keyvalue host call answered by Elixir (kvcounter_unpriv can't be
+      # used for this, its keyvalue calls are rejected natively for lack of a capability claim).
+      # With far more calls in flight than there are tokio workers, they only all finish if
+      # waiting doesn't tie up a worker
+      num_calls = System.schedulers_online() * 50
+
+      results =
+        1..num_calls
+        |> Task.async_stream(
+          fn _ -> invoke(runtime, aref) end,
+          max_concurrency: num_calls,
+          timeout: 30_000
+        )
+        |> Enum.map(fn {:ok, res} -> res end)
+
+      assert length(results) == num_calls
+
+      # there's no link definition for keyvalue, so each call completes with an error response
+      assert Enum.all?(results, fn {:ok, resp, _metrics} ->
+               Msgpax.unpack!(resp)["statusCode"] == 500
+             end)
+    end
+
+    test "host calls without a capability claim are rejected by the runtime", %{
+      :hconfig => config,
+      :host_pid => pid
+    } do
+      on_exit(fn -> cleanup(pid, config) end)
+
+      {:ok, runtime} = RuntimeServer.runtime_for_host(config.host_key)
+      aref = start_actor(runtime, @kvcounter_unpriv_path)
+
+      {:ok, resp, _metrics} = invoke(runtime, aref)
+
+      resp = Msgpax.unpack!(resp)
+      assert resp["statusCode"] == 500
+      assert resp["body"] =~ "missing capability claim for wasmcloud:keyvalue"
+    end
+  end
+
+  describe "Builtin numbergen" do
+    # the generated values only reach the actor, their reproducibility is covered by the native
+    # numbergen tests
+    test "serves the actors of a seeded runtime across reseeding" do
+      runtime = start_runtime(numbergen_seed: 42)
+      aref = start_actor(runtime, @randogenlogger_path)
+
+      {:ok, resp, _metrics} = invoke(runtime, aref)
+      assert Msgpax.unpack!(resp)["body"] == "I did it"
+
+      :ok = RuntimeServer.reseed_numbergen(runtime, 42)
+      {:ok, resp, _metrics} = invoke(runtime, aref)
+      assert Msgpax.unpack!(resp)["body"] == "I did it"
+    end
+  end
+
+  describe "Builtin keyvalue" do
+    test "serves keyvalue host calls without a provider" do
+      runtime = start_runtime(builtin_keyvalue: true)
+      aref = start_actor(runtime, @kvcounter_path)
+
+      counters =
+        for _ <- 1..3 do
+          {:ok, resp, _metrics} = invoke(runtime, aref)
+
+          resp = Msgpax.unpack!(resp)
+          assert resp["statusCode"] == 200
+          Jason.decode!(resp["body"])["counter"]
+        end
+
+      assert counters == [1, 2, 3]
+    end
+
+    test "still requires the keyvalue capability claim" do
+      runtime = start_runtime(builtin_keyvalue: true)
+      aref = start_actor(runtime, @kvcounter_unpriv_path)
+
+      {:ok, resp, _metrics} = invoke(runtime, aref)
+
+      resp = Msgpax.unpack!(resp)
+      assert resp["statusCode"] == 500
+      assert resp["body"] =~ "missing capability claim for wasmcloud:keyvalue"
+    end
+  end
+
+  describe "Call metrics" do
+    test "account for the call and the host calls it made" do
+      runtime = start_runtime(builtin_keyvalue: true)
+      aref = start_actor(runtime, @kvcounter_path)
+
+      {:ok, resp, metrics} = invoke(runtime, aref)
+
+      assert metrics.bytes_in == byte_size(http_request())
+      assert metrics.bytes_out == byte_size(resp)
+      # at least the keyvalue increment
+      assert metrics.host_calls >= 1
+      assert metrics.host_call_bytes_out > 0
+      assert metrics.host_call_bytes_in > 0
+      assert metrics.guest_cpu_time_us <= metrics.wall_time_us
+      assert metrics.fuel_consumed == nil
+      assert metrics.peak_memory_bytes > 0
+    end
+
+    test "report fuel when the runtime meters it" do
+      runtime = start_runtime(builtin_keyvalue: true, fuel_metering: true)
+      aref = start_actor(runtime, @kvcounter_path)
+
+      {:ok, _resp, metrics} = invoke(runtime, aref)
+
+      assert is_integer(metrics.fuel_consumed)
+      assert metrics.fuel_consumed > 0
+    end
+  end
+
+  describe "Runtime stats" do
+    test "report started actors and completed calls" do
+      runtime = start_runtime(builtin_keyvalue: true)
+      aref = start_actor(runtime, @kvcounter_path)
+
+      stats = RuntimeServer.stats(runtime)
+      assert stats.actors == 1
+      assert [%{instances: 1, in_flight: 0}] = Map.values(stats.per_actor)
+      assert stats.workers > 0
+
+      {:ok, _resp, _metrics} = invoke(runtime, aref)
+
+      stats = RuntimeServer.stats(runtime)
+      assert stats.calls == 1
+      assert stats.call_errors == 0
+      assert stats.in_flight == 0
+      assert stats.queued == 0
+      assert stats.pending_callbacks == 0
+
+      # an error returned by the actor itself reaches the caller as one
+      assert {:error, _msg} = invoke(runtime, aref, "HttpServer.NoSuchOperation")
+
+      assert %{calls: 2, call_errors: 1} = RuntimeServer.stats(runtime)
+    end
+  end
+
+  describe "Cancelling actor calls" do
+    test "interrupts a timed out actor that never yields to the host" do
+      runtime = start_runtime(interruptible_calls: true)
+
+      assert %{interruptible_calls: true, guest_profiling: false} =
+               RuntimeServer.engine_config(runtime)
+
+      aref = start_actor(runtime, @looper_path)
+
+      assert {:error, :timeout} =
+               RuntimeServer.invoke_actor(runtime, aref, "Loop", "", "", UUID.uuid4(), 100)
+
+      # the spinning call only lets the runtime drain if it was interrupted
+      assert :ok = RuntimeServer.drain(runtime, 1_000)
+      assert %{in_flight: 0, calls_cancelled: 1} = RuntimeServer.stats(runtime)
+    end
+  end
+
+  describe "Actor health checks" do
+    test "report the health of a running actor and fail once it is draining" do
+      runtime = start_runtime()
+      aref = start_actor(runtime, @kvcounter_path)
+
+      assert :ok = Runtime.health_check_actor(aref, 1_000)
+      assert_receive {:actor_health, %{healthy: true}}, 2_000
+
+      :ok = Runtime.drain_actor(aref, 1_000)
+
+      assert :ok = Runtime.health_check_actor(aref, 1_000)
+      assert_receive {:actor_health, %{healthy: false, message: "actor is draining"}}, 2_000
+    end
+  end
+
+  describe "Recording actor calls" do
+    test "replays them without the host, reporting where the actor diverges" do
+      runtime = start_runtime(builtin_keyvalue: true)
+      aref = start_actor(runtime, @kvcounter_path)
+      path = Path.join(System.tmp_dir!(), "kvcounter-#{UUID.uuid4()}.rec")
+      on_exit(fn -> File.rm(path) end)
+
+      :ok = Runtime.start_recording(aref, path)
+      assert {:error, _} = Runtime.start_recording(aref, path)
+
+      {:ok, _resp, _metrics} = invoke(runtime, aref)
+
+      assert {:ok, 1} = Runtime.stop_recording(aref)
+      stop_supervised!(RuntimeServer)
+
+      {:ok, report} = Runtime.replay_recording(File.read!(@kvcounter_path), path)
+      assert %{calls: 1, module_changed: false, divergences: []} = report
+      assert report.host_calls > 0
+
+      {:ok, pinger} = File.read(@pinger_path)
+      {:ok, report} = Runtime.replay_recording(pinger, path)
+      assert report.module_changed
+      assert report.divergences != []
+    end
+  end
+
+  describe "Guest profiling" do
+    test "samples the profiled calls into a profile file" do
+      runtime = start_runtime(builtin_keyvalue: true, guest_profiling: true)
+
+      assert %{guest_profiling: true} = RuntimeServer.engine_config(runtime)
+
+      aref = start_actor(runtime, @kvcounter_path)
+      path = Path.join(System.tmp_dir!(), "kvcounter-#{UUID.uuid4()}.json")
+      on_exit(fn -> File.rm(path) end)
+
+      assert {:error, _} = Runtime.start_profiling(aref, path, 0)
+      :ok = Runtime.start_profiling(aref, path, 1)
+      assert {:error, _} = Runtime.start_profiling(aref, path, 1)
+
+      for _ <- 1..2 do
+        {:ok, _resp, _metrics} = invoke(runtime, aref)
+      end
+
+      assert {:ok, %{path: ^path, calls: 1}} = Runtime.stop_profiling(aref)
+      assert {:error, _} = Runtime.stop_profiling(aref)
+
+      %{"threads" => [thread]} = path |> File.read!() |> Jason.decode!()
+      assert is_list(thread["samples"]["data"])
+    end
+
+    test "is only available on runtimes that enable it" do
+      runtime = start_runtime()
+      aref = start_actor(runtime, @kvcounter_path)
+      path = Path.join(System.tmp_dir!(), "kvcounter-#{UUID.uuid4()}.json")
+
+      assert {:error, err} = Runtime.start_profiling(aref, path, 1)
+      assert err =~ "not enabled"
+      refute File.exists?(path)
+    end
+  end
+
+  describe "Live updates" do
+    test "reject modules that are not a newer revision of the running actor" do
+      runtime = start_runtime(builtin_keyvalue: true)
+
+      {:ok, bytes} = File.read(@kvcounter_path)
+      {:ok, pinger} = File.read(@pinger_path)
+      {:ok, aref} = RuntimeServer.precompile_actor(runtime, bytes)
+
+      assert {:error, err} = RuntimeServer.update_actor(runtime, aref, bytes)
+      assert err =~ "revision"
+      assert {:error, err} = RuntimeServer.update_actor(runtime, aref, pinger)
+      assert err =~ "rather than"
+
+      # the actor keeps running the module it was started with
+      {:ok, _resp, _metrics} = invoke(runtime, aref)
+    end
+  end
+
+  describe "Actor concurrency limits" do
+    test "queue calls past max_concurrency and reject them past max_queued" do
+      runtime = start_runtime(callback_timeout_ms: 2_000)
+
+      aref =
+        start_actor(runtime, @kvcounter_path, %ActorConfig{max_concurrency: 1, max_queued: 1})
+
+      # with the runtime server suspended, the first call stays blocked on its keyvalue host call
+      :ok = :sys.suspend(runtime)
+
+      call = fn ->
+        Runtime.call_actor(
+          aref,
+          "HttpServer.HandleRequest",
+          http_request(),
+          call_context(),
+          UUID.uuid4(),
+          {self(), make_ref()}
+        )
+      end
+
+      assert {:ok, _running} = call.()
+      assert {:ok, _queued} = call.()
+      assert {:error, :busy} = call.()
+
+      # system messages are still handled while the server is suspended
+      {rt, _config, _calls} = :sys.get_state(runtime)
+      assert %{in_flight: 1, queued: 1, per_actor: per_actor} = Runtime.stats(rt)
+      assert [%{in_flight: 1, queued: 1}] = Map.values(per_actor)
+
+      :ok = :sys.resume(runtime)
+
+      # the NIF replies to the process that made the call
+      assert_receive {:returned_function_call, {:ok, _, _}, _from}, 5_000
+      assert_receive {:returned_function_call, {:ok, _, _}, _from}, 5_000
+    end
+  end
+
+  describe "Engine configuration" do
+    test "reports the settings the engine runs with" do
+      runtime = start_runtime(opt_level: :none, max_wasm_stack: 1024 * 1024)
+
+      assert %{
+               opt_level: :none,
+               parallel_compilation: true,
+               pooling_allocator: false,
+               max_wasm_stack: 1_048_576,
+               module_cache_dir: nil
+             } = config = RuntimeServer.engine_config(runtime)
+
+      assert :simd in config.wasm_proposals
+      refute :threads in config.wasm_proposals
+
+      # the engine still runs actors as configured
+      _aref = start_actor(runtime, @kvcounter_path)
+    end
+
+    test "rejects inconsistent settings" do
+      assert {:error, "invalid engine configuration: " <> _} =
+               Runtime.new(%RuntimeConfig{host_id: "Nxxx", wasm_proposals: [:reference_types]})
+
+      assert {:error, _} = Runtime.new(%RuntimeConfig{host_id: "Nxxx", max_wasm_stack: 0})
+    end
+  end
+
+  describe "Call contexts" do
+    test "round trip through their encoding and reject anything else" do
+      context = %CallContext{
+        trace_context: %{
+          "traceparent" => "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"
+        },
+        deadline_ms: 1_700_000_000_000,
+        caller: %{public_key: "MCALLER", contract_id: "", link_name: ""},
+        invocation_id: UUID.uuid4()
+      }
+
+      {:ok, bytes} = Runtime.encode_call_context(context)
+      assert {:ok, ^context} = Runtime.decode_call_context(bytes)
+
+      assert {:error, _} =
+               Runtime.decode_call_context(:erlang.term_to_binary(Tracer.current_span_ctx()))
+    end
+  end
+
+  describe "Module inspection" do
+    test "reports the structure of an actor module" do
+      {:ok, bytes} = File.read(@kvcounter_path)
+      {:ok, info} = Runtime.inspect_module(bytes)
+
+      assert info.abi == :wasmbus
+      assert info.size_bytes == byte_size(bytes)
+      assert info.sha256 == Base.encode16(:crypto.hash(:sha256, bytes), case: :lower)
+      assert Enum.any?(info.exports, &(&1.name == "__guest_call"))
+      assert Enum.any?(info.imports, &(&1.module == "wasmbus"))
+      assert Enum.any?(info.custom_sections, &(&1.name == "jwt"))
+      assert info.memories != []
+
+      assert {:error, "Failed to inspect module: " <> _} = Runtime.inspect_module("not wasm")
+    end
+  end
+
+  describe "Component actors" do
+    test "are started and have their claims read like modules" do
+      {:ok, bytes} = File.read(@noop_component_path)
+
+      {:ok, claims} = Native.extract_claims(bytes)
+      assert claims.public_key == @noop_component_key
+      assert claims.name == "noop_component"
+
+      {:ok, info} = Runtime.inspect_module(bytes)
+      assert info.abi == :component
+
+      runtime = start_runtime()
+      assert {:ok, _aref} = RuntimeServer.precompile_actor(runtime, bytes)
+    end
+
+    test "whose bytes no longer match their claims are rejected" do
+      {:ok, bytes} = File.read(@noop_component_path)
+      # the memory's minimum size sits in the nested module, covered by the signed hash
+      tampered = :binary.replace(bytes, <<5, 3, 1, 0, 1>>, <<5, 3, 1, 0, 2>>)
+      assert tampered != bytes
+
+      assert {:error, "Failed to extract claims from module: " <> _} =
+               Native.extract_claims(tampered)
+    end
+  end
+
+  describe "Import policy" do
+    test "rejects modules with disallowed imports, listing them" do
+      {:ok, bytes} = File.read(@kvcounter_path)
+
+      runtime =
+        start_runtime(denied_imports: [%ImportRule{namespace: "wasmbus", function: "__host_*"}])
+
+      assert {:error, "module imports not allowed by the runtime's import policy: " <> rejected} =
+               RuntimeServer.precompile_actor(runtime, bytes)
+
+      assert rejected =~ "wasmbus::__host_call"
+      refute rejected =~ "__guest_request"
+      stop_supervised!(RuntimeServer)
+
+      runtime = start_runtime(allowed_imports: [%ImportRule{namespace: "wasmbus"}])
+      assert {:ok, _aref} = RuntimeServer.precompile_actor(runtime, bytes)
+    end
+  end
+
+  describe "Module cache" do
+    test "is reused by a runtime started on the same directory and can be purged" do
+      dir = Path.join(System.tmp_dir!(), "module_cache_#{UUID.uuid4()}")
+      on_exit(fn -> File.rm_rf!(dir) end)
+
+      {:ok, bytes} = File.read(@kvcounter_path)
+
+      runtime = start_runtime(module_cache_dir: dir)
+      {:ok, _aref} = RuntimeServer.precompile_actor(runtime, bytes)
+      stats = RuntimeServer.module_cache_stats(runtime)
+      assert %{hits: 0, misses: 1, modules: [%{compatible: true}]} = stats
+      assert stats.artifact_bytes > 0
+      stop_supervised!(RuntimeServer)
+
+      # a restarted runtime finds the module compiled by the previous one
+      runtime = start_runtime(module_cache_dir: dir)
+      {:ok, _aref} = RuntimeServer.precompile_actor(runtime, bytes)
+      assert %{hits: 1, misses: 0} = RuntimeServer.module_cache_stats(runtime)
+      stop_supervised!(RuntimeServer)
+
+      # an engine configured to compile different code can't use that artifact
+      runtime = start_runtime(module_cache_dir: dir, fuel_metering: true)
+      assert %{modules: [%{compatible: false}]} = RuntimeServer.module_cache_stats(runtime)
+      {:ok, _aref} = RuntimeServer.precompile_actor(runtime, bytes)
+
+      assert %{hits: 0, misses: 1, modules: [%{compatible: true, builds: [_, _]}]} =
+               RuntimeServer.module_cache_stats(runtime)
+
+      assert {:ok, 1} = RuntimeServer.purge_module_cache(runtime)
+      assert %{modules: [], artifact_bytes: 0} = RuntimeServer.module_cache_stats(runtime)
+    end
+
+    test "is not used unless a directory is configured" do
+      runtime = start_runtime()
+
+      assert RuntimeServer.module_cache_stats(runtime) == nil
+      assert {:ok, 0} = RuntimeServer.purge_module_cache(runtime)
+    end
+  end
+
+  describe "WASI configuration" do
+    test "gives actors their environment and keeps preopens inside the sandbox" do
+      sandbox = Path.join(System.tmp_dir!(), "wasi_sandbox_#{UUID.uuid4()}")
+      File.mkdir_p!(Path.join(sandbox, "assets"))
+      on_exit(fn -> File.rm_rf!(sandbox) end)
+
+      runtime = start_runtime(builtin_keyvalue: true, wasi_sandbox_dir: sandbox)
+
+      {:ok, bytes} = File.read(@kvcounter_path)
+
+      {:ok, aref} =
+        RuntimeServer.precompile_actor(runtime, bytes, %ActorConfig{
+          wasi: %WasiConfig{env: %{"MODE" => "test"}, args: ["kvcounter"], preopens: ["assets"]}
+        })
+
+      {:ok, resp, _metrics} = invoke(runtime, aref)
+
+      assert Msgpax.unpack!(resp)["statusCode"] == 200
+
+      for preopens <- [["missing"], [".."]] do
+        assert {:error, "invalid WASI configuration: " <> _} =
+                 RuntimeServer.precompile_actor(runtime, bytes, %ActorConfig{
+                   wasi: %WasiConfig{preopens: preopens}
+                 })
+      end
+    end
+  end
+
+  describe "Local actor calls" do
+    test "are delivered within the runtime when enabled" do
+      {cluster_key, cluster_seed} = Native.generate_key(:cluster)
+
+      runtime =
+        start_runtime(
+          local_actor_calls: true,
+          cluster_seed: cluster_seed,
+          cluster_issuers: [cluster_key]
+        )
+
+      ponger = start_actor(runtime, "test/fixtures/actors/ponger_s.wasm")
+      :ok = Runtime.register_local_actor(ponger)
+
+      pinger = start_actor(runtime, @pinger_path)
+
+      # the runtime isn't part of a host or lattice, so pinger can only reach ponger directly
+      {:ok, resp, _metrics} = invoke(runtime, pinger)
+
+      resp = Msgpax.unpack!(resp)
+      assert resp["statusCode"] == 200
+      assert resp["body"] == "Ping pong"
+
+      assert Runtime.unregister_local_actor(ponger)
+      refute Runtime.unregister_local_actor(ponger)
+    end
+
+    test "are rejected when signed by an issuer that isn't trusted" do
+      {_cluster_key, cluster_seed} = Native.generate_key(:cluster)
+      {other_cluster_key, _seed} = Native.generate_key(:cluster)
+
+      runtime =
+        start_runtime(
+          local_actor_calls: true,
+          cluster_seed: cluster_seed,
+          cluster_issuers: [other_cluster_key]
+        )
+
+      ponger = start_actor(runtime, "test/fixtures/actors/ponger_s.wasm")
+      :ok = Runtime.register_local_actor(ponger)
+
+      pinger = start_actor(runtime, @pinger_path)
+
+      # ponger never runs, whether pinger fails the call or answers with an error of its own
+      case invoke(runtime, pinger) do
+        {:ok, resp, _metrics} -> refute Msgpax.unpack!(resp)["body"] == "Ping pong"
+        {:error, _msg} -> :ok
+      end
+    end
+  end
+
+  # Starts a runtime server that is stopped when the test exits
+  defp start_runtime(config \\ []) do
+    start_supervised!({RuntimeServer, struct!(RuntimeConfig, [{:host_id, "Nxxx"} | config])})
+  end
+
+  defp start_actor(runtime, path, actor_config \\ %ActorConfig{}) do
+    {:ok, aref} = RuntimeServer.precompile_actor(runtime, File.read!(path), actor_config)
+    aref
+  end
+
+  defp invoke(runtime, aref, operation \\ "HttpServer.HandleRequest") do
+    RuntimeServer.invoke_actor(
+      runtime,
+      aref,
+      operation,
+      http_request(),
+      call_context(),
+      UUID.uuid4()
+    )
+  end
+
+  # Encoded the way the host does for the calls it makes, carrying the test's trace context
+  defp call_context do
+    {:ok, context} =
+      Runtime.encode_call_context(%CallContext{
+        trace_context: Map.new(:otel_propagator_text_map.inject([]))
+      })
+
+    context
+  end
+
+  defp http_request do
+    %{
+      body: "hello",
+      header: %{},
+      path: "/api/counter",
+      queryString: "",
+      method: "GET"
+    }
+    |> Msgpax.pack!()
+    |> IO.iodata_to_binary()
+  end
+end
@@ -74,4 +74,23 @@ defmodule HostCoreTest do
     assert actor_count == 0
     assert ProviderSupervisor.all_providers(config.host_key) == []
   end
+
+  test "Host isn't started with engine settings it doesn't know", %{
+    :hconfig => config,
+    :host_pid => pid
+  } do
+    on_exit(fn -> cleanup(pid, config) end)
+
+    bad_config = %{HostCoreTest.Common.default_vhost_config() | engine_opt_level: "fastest"}
+    assert {:stop, {:invalid_config, e}} = VirtualHost.init(bad_config)
+    assert e =~ "engine_opt_level \"fastest\""
+
+    bad_config = %{
+      HostCoreTest.Common.default_vhost_config()
+      | engine_wasm_proposals: ["simd", "tail_call"]
+    }
+
+    assert {:stop, {:invalid_config, e}} = VirtualHost.init(bad_config)
+    assert e =~ "engine_wasm_proposals \"tail_call\""
+  end
 end
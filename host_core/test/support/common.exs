@@ -131,7 +131,17 @@ defmodule HostCoreTest.Common do
       enable_structured_logging: false,
       log_level: :info,
       enable_ipv6: false,
+      enable_local_actor_calls: false,
       enable_start_from_fs: true,
+      module_cache_dir: nil,
+      engine_opt_level: nil,
+      engine_parallel_compilation: nil,
+      engine_pooling_allocator: nil,
+      engine_max_wasm_stack: nil,
+      engine_wasm_proposals: nil,
+      engine_guest_profiling: nil,
+      engine_interruptible_calls: nil,
+      engine_fuel_metering: nil,
       policy_topic: nil,
       policy_changes_topic: nil,
       policy_timeout_ms: 1_000
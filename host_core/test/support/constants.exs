@@ -17,6 +17,11 @@ defmodule HostCoreTest.Constants do
   @kvcounter_unpriv_path "test/fixtures/actors/kvcounter_unpriv_s.wasm"
   @kvcounter_unpriv_filepath "file://test/fixtures/actors/kvcounter_unpriv_s.wasm"
 
+  @looper_path "test/fixtures/actors/looper_s.wasm"
+
+  @noop_component_path "test/fixtures/actors/noop_component_s.wasm"
+  @noop_component_key "MBWQSXH5FUDG77YWZZW3HNENMKXHTYHVHYFSGVXO62SFPFIOF4BQQOT3"
+
   @pinger_path "test/fixtures/actors/pinger_s.wasm"
   @pinger_key "MBXTD7YXTTV7IURPDDACK3E6YZ2XPJNSG3LQQRHUCHJVZ54F6E4FMB3B"
 
@@ -63,6 +68,9 @@ defmodule HostCoreTest.Constants do
   def kvcounter_unpriv_filepath, do: @kvcounter_unpriv_filepath
   def kvcounter_unpriv_key, do: @kvcounter_unpriv_key
   def kvcounter_unpriv_path, do: @kvcounter_unpriv_path
+  def looper_path, do: @looper_path
+  def noop_component_path, do: @noop_component_path
+  def noop_component_key, do: @noop_component_key
   def pinger_path, do: @pinger_path
   def pinger_key, do: @pinger_key
   def policy_path, do: @policy_path